@@ -23,6 +23,7 @@ cargo publish --allow-dirty --dry-run
 echo ''
 
 check serde
+check vendor
 check latest
 check legacy
 check linux-6.13
@@ -245,6 +245,7 @@ pub(crate) fn from(event_cfg: EventConfig, opts: &Opts) -> Result<Attr> {
     when!(cpu, PERF_SAMPLE_CPU);
     when!(task, PERF_SAMPLE_TID);
     when!(time, PERF_SAMPLE_TIME);
+    when!(identifier, PERF_SAMPLE_IDENTIFIER);
     attr.sample_type = sample_type as _;
 
     macro_rules! when {
@@ -325,6 +326,11 @@ pub(crate) fn from(event_cfg: EventConfig, opts: &Opts) -> Result<Attr> {
     #[cfg(not(feature = "linux-4.1"))]
     crate::config::unsupported!(opts.timer.is_some());
 
+    #[cfg(feature = "linux-4.7")]
+    attr.set_write_backward(opts.write_backward as _);
+    #[cfg(not(feature = "linux-4.7"))]
+    crate::config::unsupported!(opts.write_backward);
+
     #[cfg(feature = "linux-6.13")]
     {
         let aux_action = unsafe { &mut attr.__bindgen_anon_5.__bindgen_anon_1 };
@@ -0,0 +1,418 @@
+#[cfg(test)]
+mod test;
+
+use std::io::{Error, ErrorKind, Result};
+use std::sync::OnceLock;
+
+use super::{attr, sibling, Opts};
+use crate::event::sw::Software;
+use crate::event::Event;
+use crate::ffi::{bindings as b, syscall, Attr, PAGE_SIZE};
+
+/// Capabilities of the running kernel.
+///
+/// The `linux-X.Y` features decide which options can be built at compile time,
+/// this type tells which of them are accepted by the running kernel, so that one
+/// binary can be shipped to hosts with different kernels.
+///
+/// Each option is probed by opening a dummy software event with the option enabled,
+/// and is `None` if the probe is inconclusive, e.g., the dummy event can't be opened
+/// due to `perf_event_paranoid`. Options that can't be probed this way (e.g., options
+/// that only work with AUX events) are not listed here, and are left to the kernel.
+/// The kernel release is not used, since vendors may backport features to older kernels.
+///
+/// Counters don't check their options on creation, use [`KernelCaps::check`] to get
+/// [`ErrorKind::Unsupported`] naming the option if it is known to be rejected by the
+/// running kernel, instead of a bare `EINVAL` from the kernel.
+///
+/// # Examples
+///
+/// ```rust
+/// use perf_event_open::config::{KernelCaps, OnExecve, Opts, SampleOn, SigData};
+///
+/// let caps = KernelCaps::current();
+///
+/// let mut opts = Opts::default();
+/// opts.sample_on = SampleOn::Freq(1000);
+/// // Fall back to other mechanism on older kernels.
+/// if caps.sigtrap == Some(true) {
+///     opts.sigtrap_on_sample = Some(SigData(0));
+///     opts.on_execve = Some(OnExecve::Remove);
+/// }
+/// caps.check(&opts).unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KernelCaps {
+    /// Size of `perf_event_attr` known by the kernel.
+    ///
+    /// Options stored beyond this size are rejected by [`KernelCaps::check`].
+    /// `None` if it can't be probed.
+    pub attr_size: Option<u32>,
+
+    /// Accepts [`Opts::timer`][super::Opts::timer].
+    pub use_clockid: Option<bool>,
+
+    /// Accepts [`ExtraRecord::ctx_switch`][super::ExtraRecord::ctx_switch].
+    pub context_switch: Option<bool>,
+
+    /// Accepts [`Opts::write_backward`][super::Opts::write_backward].
+    pub write_backward: Option<bool>,
+
+    /// Accepts [`ExtraRecord::namespaces`][super::ExtraRecord::namespaces].
+    pub namespaces: Option<bool>,
+
+    /// Accepts [`SampleFormat::data_phys_addr`][super::SampleFormat::data_phys_addr].
+    pub sample_phys_addr: Option<bool>,
+
+    /// Accepts [`ExtraRecord::ksymbol`][super::ExtraRecord::ksymbol].
+    pub ksymbol: Option<bool>,
+
+    /// Accepts [`ExtraRecord::bpf_event`][super::ExtraRecord::bpf_event].
+    pub bpf_event: Option<bool>,
+
+    /// Accepts [`SampleFormat::aux`][super::SampleFormat::aux].
+    pub sample_aux: Option<bool>,
+
+    /// Accepts [`ExtraRecord::cgroup`][super::ExtraRecord::cgroup].
+    pub cgroup: Option<bool>,
+
+    /// Accepts [`SampleFormat::cgroup`][super::SampleFormat::cgroup].
+    pub sample_cgroup: Option<bool>,
+
+    /// Accepts [`ExtraRecord::text_poke`][super::ExtraRecord::text_poke].
+    pub text_poke: Option<bool>,
+
+    /// Accepts [`SampleFormat::data_page_size`][super::SampleFormat::data_page_size].
+    pub sample_data_page_size: Option<bool>,
+
+    /// Accepts [`SampleFormat::code_page_size`][super::SampleFormat::code_page_size].
+    pub sample_code_page_size: Option<bool>,
+
+    /// Accepts [`Repr::Vars`][super::Repr::Vars] of [`SampleFormat::weight`][super::SampleFormat::weight].
+    pub sample_weight_struct: Option<bool>,
+
+    /// Accepts [`UseBuildId`][super::UseBuildId].
+    pub build_id: Option<bool>,
+
+    /// Accepts [`Inherit::NewThread`][super::Inherit::NewThread].
+    pub inherit_thread: Option<bool>,
+
+    /// Accepts [`OnExecve::Remove`][super::OnExecve::Remove].
+    pub remove_on_exec: Option<bool>,
+
+    /// Accepts [`Opts::sigtrap_on_sample`][super::Opts::sigtrap_on_sample].
+    pub sigtrap: Option<bool>,
+
+    /// Accepts [`Opts::pause_aux`][super::Opts::pause_aux] and
+    /// [`OnSample::aux`][super::sibling::OnSample::aux].
+    pub aux_action: Option<bool>,
+}
+
+impl KernelCaps {
+    /// Probes the capabilities of the running kernel.
+    ///
+    /// This opens a few dummy events, use [`KernelCaps::current`] for the cached result.
+    pub fn detect() -> Self {
+        // The dummy event can't be opened if `perf_event_paranoid` is 3 (e.g., on Debian),
+        // or `perf_event_open` is filtered by seccomp.
+        #[allow(unused_variables)]
+        let probing = try_open(|_| ()) == Some(true);
+
+        macro_rules! probe {
+            ($feature:literal, |$attr:ident| $set:block) => {{
+                #[cfg(feature = $feature)]
+                let val = match probing {
+                    true => try_open(|$attr| $set),
+                    false => None,
+                };
+                #[cfg(not(feature = $feature))]
+                let val = None;
+                val
+            }};
+        }
+
+        Self {
+            attr_size: attr_size(),
+            use_clockid: probe!("linux-4.1", |attr| {
+                attr.set_use_clockid(1);
+                attr.clockid = b::CLOCK_MONOTONIC as _;
+            }),
+            context_switch: probe!("linux-4.3", |attr| { attr.set_context_switch(1) }),
+            write_backward: probe!("linux-4.7", |attr| { attr.set_write_backward(1) }),
+            namespaces: probe!("linux-4.12", |attr| { attr.set_namespaces(1) }),
+            sample_phys_addr: probe!("linux-4.14", |attr| {
+                attr.sample_type = b::PERF_SAMPLE_PHYS_ADDR as _;
+            }),
+            ksymbol: probe!("linux-5.1", |attr| { attr.set_ksymbol(1) }),
+            bpf_event: probe!("linux-5.1", |attr| { attr.set_bpf_event(1) }),
+            sample_aux: probe!("linux-5.5", |attr| {
+                attr.sample_type = b::PERF_SAMPLE_AUX as _;
+            }),
+            cgroup: probe!("linux-5.7", |attr| { attr.set_cgroup(1) }),
+            sample_cgroup: probe!("linux-5.7", |attr| {
+                attr.sample_type = b::PERF_SAMPLE_CGROUP as _;
+            }),
+            text_poke: probe!("linux-5.9", |attr| { attr.set_text_poke(1) }),
+            sample_data_page_size: probe!("linux-5.11", |attr| {
+                attr.sample_type = b::PERF_SAMPLE_DATA_PAGE_SIZE as _;
+            }),
+            sample_code_page_size: probe!("linux-5.11", |attr| {
+                attr.sample_type = b::PERF_SAMPLE_CODE_PAGE_SIZE as _;
+            }),
+            sample_weight_struct: probe!("linux-5.12", |attr| {
+                attr.sample_type = b::PERF_SAMPLE_WEIGHT_STRUCT as _;
+            }),
+            build_id: probe!("linux-5.12", |attr| {
+                attr.set_mmap(1);
+                attr.set_mmap2(1);
+                attr.set_build_id(1);
+            }),
+            inherit_thread: probe!("linux-5.13", |attr| {
+                attr.set_inherit(1);
+                attr.set_inherit_thread(1);
+            }),
+            remove_on_exec: probe!("linux-5.13", |attr| { attr.set_remove_on_exec(1) }),
+            sigtrap: probe!("linux-5.13", |attr| {
+                attr.set_remove_on_exec(1);
+                attr.set_sigtrap(1);
+            }),
+            // Software events don't support pausing AUX, which results in `EOPNOTSUPP`
+            // instead of `EINVAL` on kernels accepting the field.
+            aux_action: probe!("linux-6.13", |attr| {
+                let aux_action = unsafe { &mut attr.__bindgen_anon_5.__bindgen_anon_1 };
+                aux_action.set_aux_start_paused(1);
+            }),
+        }
+    }
+
+    /// Returns the capabilities of the running kernel.
+    ///
+    /// The capabilities are probed on the first call, see [`KernelCaps::detect`].
+    pub fn current() -> &'static Self {
+        static CAPS: OnceLock<KernelCaps> = OnceLock::new();
+        CAPS.get_or_init(Self::detect)
+    }
+
+    /// Checks the options against the capabilities.
+    ///
+    /// Returns [`ErrorKind::Unsupported`] naming the option if it is known to be
+    /// rejected by the running kernel, options that can't be probed are left to the kernel.
+    pub fn check(&self, opts: &Opts) -> Result<()> {
+        let attr = attr::from(dummy()?, opts)?;
+        self.check_attr(&attr)
+    }
+
+    /// Checks the sibling options against the capabilities, see [`KernelCaps::check`].
+    pub fn check_sibling(&self, opts: &sibling::Opts) -> Result<()> {
+        let attr = sibling::attr::from(dummy()?, opts, &Attr::default())?;
+        self.check_attr(&attr)
+    }
+
+    fn check_attr(&self, attr: &Attr) -> Result<()> {
+        macro_rules! require {
+            ($feature:literal, $cap:expr, $used:expr, $option:literal) => {
+                #[cfg(feature = $feature)]
+                if $used && $cap == Some(false) {
+                    let error = format!(
+                        "{} is not supported by the running kernel (since `{}`)",
+                        $option, $feature
+                    );
+                    return Err(Error::new(ErrorKind::Unsupported, error));
+                }
+            };
+        }
+        #[allow(unused_macros)]
+        macro_rules! sample {
+            ($flag:ident) => {
+                attr.sample_type & b::$flag as u64 > 0
+            };
+        }
+
+        require!(
+            "linux-4.1",
+            self.use_clockid,
+            attr.use_clockid() > 0,
+            "`Opts::timer`"
+        );
+        require!(
+            "linux-4.3",
+            self.context_switch,
+            attr.context_switch() > 0,
+            "`ExtraRecord::ctx_switch`"
+        );
+        require!(
+            "linux-4.7",
+            self.write_backward,
+            attr.write_backward() > 0,
+            "`Opts::write_backward`"
+        );
+        require!(
+            "linux-4.12",
+            self.namespaces,
+            attr.namespaces() > 0,
+            "`ExtraRecord::namespaces`"
+        );
+        require!(
+            "linux-4.14",
+            self.sample_phys_addr,
+            sample!(PERF_SAMPLE_PHYS_ADDR),
+            "`SampleFormat::data_phys_addr`"
+        );
+        require!(
+            "linux-5.1",
+            self.ksymbol,
+            attr.ksymbol() > 0,
+            "`ExtraRecord::ksymbol`"
+        );
+        require!(
+            "linux-5.1",
+            self.bpf_event,
+            attr.bpf_event() > 0,
+            "`ExtraRecord::bpf_event`"
+        );
+        require!(
+            "linux-5.5",
+            self.sample_aux,
+            sample!(PERF_SAMPLE_AUX),
+            "`SampleFormat::aux`"
+        );
+        require!(
+            "linux-5.7",
+            self.cgroup,
+            attr.cgroup() > 0,
+            "`ExtraRecord::cgroup`"
+        );
+        require!(
+            "linux-5.7",
+            self.sample_cgroup,
+            sample!(PERF_SAMPLE_CGROUP),
+            "`SampleFormat::cgroup`"
+        );
+        require!(
+            "linux-5.9",
+            self.text_poke,
+            attr.text_poke() > 0,
+            "`ExtraRecord::text_poke`"
+        );
+        require!(
+            "linux-5.11",
+            self.sample_data_page_size,
+            sample!(PERF_SAMPLE_DATA_PAGE_SIZE),
+            "`SampleFormat::data_page_size`"
+        );
+        require!(
+            "linux-5.11",
+            self.sample_code_page_size,
+            sample!(PERF_SAMPLE_CODE_PAGE_SIZE),
+            "`SampleFormat::code_page_size`"
+        );
+        require!(
+            "linux-5.12",
+            self.sample_weight_struct,
+            sample!(PERF_SAMPLE_WEIGHT_STRUCT),
+            "`Repr::Vars`"
+        );
+        require!(
+            "linux-5.12",
+            self.build_id,
+            attr.build_id() > 0,
+            "`UseBuildId`"
+        );
+        require!(
+            "linux-5.13",
+            self.inherit_thread,
+            attr.inherit_thread() > 0,
+            "`Inherit::NewThread`"
+        );
+        require!(
+            "linux-5.13",
+            self.remove_on_exec,
+            attr.remove_on_exec() > 0,
+            "`OnExecve::Remove`"
+        );
+        require!(
+            "linux-5.13",
+            self.sigtrap,
+            attr.sigtrap() > 0,
+            "`Opts::sigtrap_on_sample` or `OnSample::sigtrap`"
+        );
+        require!(
+            "linux-6.3",
+            self.attr_size.map(|it| it >= b::PERF_ATTR_SIZE_VER8),
+            attr.config3 > 0,
+            "`config3` of the event"
+        );
+        require!(
+            "linux-6.13",
+            self.aux_action,
+            unsafe { attr.__bindgen_anon_5.aux_action } > 0,
+            "`Opts::pause_aux` or `OnSample::aux`"
+        );
+
+        // Attrs are always passed with our full size, the kernel accepts a larger attr as long as
+        // the fields unknown to it are zero, and returns `E2BIG` otherwise:
+        // https://github.com/torvalds/linux/blob/v6.13/kernel/events/core.c
+        let Some(size) = self.attr_size else {
+            return Ok(());
+        };
+        let size = (size as usize).min(size_of::<Attr>());
+        let bytes = unsafe {
+            std::slice::from_raw_parts(attr as *const Attr as *const u8, size_of::<Attr>())
+        };
+        if bytes[size..].iter().any(|it| *it != 0) {
+            let error = format!(
+                "Options beyond the first {} bytes of `perf_event_attr` are not supported by the running kernel",
+                size
+            );
+            return Err(Error::new(ErrorKind::Unsupported, error));
+        }
+
+        Ok(())
+    }
+}
+
+// The event doesn't matter, only the options are checked.
+fn dummy() -> Result<crate::event::EventConfig> {
+    Event::try_from(Software::Dummy).map(|it| it.0)
+}
+
+// The kernel returns `E2BIG` with its attr size if the size is larger than a page:
+// https://github.com/torvalds/linux/blob/v6.13/kernel/events/core.c
+fn attr_size() -> Option<u32> {
+    let mut attr = Attr {
+        size: *PAGE_SIZE as u32 + 1,
+        ..Default::default()
+    };
+    let flags = b::PERF_FLAG_FD_CLOEXEC as u64;
+    match syscall!(perf_event_open_mut, &mut attr, 0, -1, -1, flags) {
+        Err(e) if e.raw_os_error() == Some(libc::E2BIG) => Some(attr.size),
+        _ => None,
+    }
+}
+
+// Returns `None` if the result is inconclusive, e.g., `EACCES`.
+fn try_open(set: impl FnOnce(&mut Attr)) -> Option<bool> {
+    let mut attr = Attr {
+        size: size_of::<Attr>() as _,
+        type_: b::PERF_TYPE_SOFTWARE,
+        config: b::PERF_COUNT_SW_DUMMY as _,
+        ..Default::default()
+    };
+    attr.set_disabled(1);
+    attr.set_exclude_kernel(1);
+    attr.set_exclude_hv(1);
+    set(&mut attr);
+
+    let flags = b::PERF_FLAG_FD_CLOEXEC as u64;
+    match syscall!(perf_event_open, &attr, 0, -1, -1, flags) {
+        Ok(_) => Some(true),
+        Err(e) => match e.raw_os_error() {
+            // Unknown fields are rejected by the kernel with `EINVAL` or `E2BIG`.
+            Some(libc::EINVAL | libc::E2BIG) => Some(false),
+            // The field is known, but not supported by the event.
+            Some(libc::EOPNOTSUPP) => Some(true),
+            _ => None,
+        },
+    }
+}
@@ -0,0 +1,56 @@
+use std::io::ErrorKind;
+
+use super::KernelCaps;
+use crate::config::{sibling, Opts};
+use crate::ffi::{bindings as b, Attr};
+
+#[test]
+fn test_detect() {
+    let caps = KernelCaps::detect();
+    assert_eq!(&caps, KernelCaps::current());
+    // Probes are inconclusive under seccomp or `perf_event_paranoid` 3.
+    if let Some(size) = caps.attr_size {
+        // `PERF_ATTR_SIZE_VER1` is since `linux-2.6.33`.
+        assert!(size >= b::PERF_ATTR_SIZE_VER1);
+    }
+    // The oldest kernel we can run tests on is much newer than `linux-4.1`.
+    #[cfg(feature = "linux-4.1")]
+    assert_ne!(caps.use_clockid, Some(false));
+}
+
+#[test]
+fn test_check() {
+    let mut caps = KernelCaps::detect();
+    caps.check(&Opts::default()).unwrap();
+    caps.check_sibling(&sibling::Opts::default()).unwrap();
+
+    #[cfg(feature = "linux-4.1")]
+    {
+        let opts = Opts {
+            timer: Some(crate::config::Clock::Monotonic),
+            ..Default::default()
+        };
+        caps.use_clockid = Some(true);
+        caps.check(&opts).unwrap();
+        // Inconclusive probes are left to the kernel.
+        caps.use_clockid = None;
+        caps.check(&opts).unwrap();
+        caps.use_clockid = Some(false);
+        let error = caps.check(&opts).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Unsupported);
+        assert!(error.to_string().contains("`Opts::timer`"));
+    }
+
+    // Fields beyond the kernel attr size are used.
+    caps = KernelCaps::detect();
+    caps.attr_size = Some(b::PERF_ATTR_SIZE_VER0);
+    let attr = Attr {
+        size: size_of::<Attr>() as _,
+        branch_sample_type: 1,
+        ..Default::default()
+    };
+    let error = caps.check_attr(&attr).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+    caps.attr_size = None;
+    caps.check_attr(&attr).unwrap();
+}
@@ -3,9 +3,11 @@ use std::io::Result;
 use crate::ffi::bindings as b;
 
 pub(super) mod attr;
+mod caps;
 pub mod sibling;
-mod target;
+pub(crate) mod target;
 
+pub use caps::*;
 pub use target::*;
 
 /// This macro will not be used under the `latest` feature, as all syscall
@@ -189,6 +191,13 @@ pub struct Opts {
     ///
     /// Since `linux-6.13`: <https://github.com/torvalds/linux/commit/18d92bb57c39504d9da11c6ef604f58eb1d5a117>
     pub pause_aux: bool,
+
+    /// Writes records into the ring buffer from end to beginning.
+    ///
+    /// This is required by [overwrite samplers][crate::count::Counter::overwrite_sampler].
+    ///
+    /// Since `linux-4.7`: <https://github.com/torvalds/linux/blob/v4.7/include/uapi/linux/perf_event.h>
+    pub write_backward: bool,
 }
 
 /// Privilege levels.
@@ -817,6 +826,14 @@ pub struct RecordIdFormat {
     // PERF_SAMPLE_TIME
     /// Contains [timestamp][crate::sample::record::RecordId::time].
     pub time: bool,
+
+    // PERF_SAMPLE_IDENTIFIER
+    /// Contains [event ID][crate::sample::record::RecordId::id] at a fixed position of records.
+    ///
+    /// Unlike [`id`][Self::id], the position does not depend on the sample format,
+    /// so records of events sharing the same ring buffer can be told apart before
+    /// parsing. This is required by [`MultiSampler`][crate::sample::MultiSampler].
+    pub identifier: bool,
 }
 
 /// Wake up options for asynchronous iterators.
@@ -1051,7 +1068,7 @@ Mmap::ext                         1 mmap2                     include mmap with
 false                             1 comm_exec                 flag comm events that are due to an exec
 Opts::timer                       1 use_clockid               use @clockid for time fields
 ExtraRecord::ctx_switch           1 context_switch            context switch data
-false                             1 write_backward            Write ring buffer from end to beginning
+Opts::write_backward              1 write_backward            Write ring buffer from end to beginning
 ExtraRecord::namespaces           1 namespaces                include namespaces data
 ExtraRecord::ksymbol              1 ksymbol                   include ksymbol events
 ExtraRecord::bpf_event            1 bpf_event                 include bpf events
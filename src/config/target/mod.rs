@@ -1,4 +1,8 @@
-use std::fs::File;
+#[cfg(test)]
+mod test;
+
+use std::fs::{self, File};
+use std::io::{Error, ErrorKind, Result};
 use std::os::fd::AsRawFd;
 
 use crate::ffi::bindings as b;
@@ -18,6 +22,32 @@ impl Cpu {
     ///
     /// This is an alias for [`All`].
     pub const ALL: All = All;
+
+    /// Returns the online CPUs from `/sys/devices/system/cpu/online`.
+    pub fn online() -> Result<Vec<Cpu>> {
+        let list = fs::read_to_string("/sys/devices/system/cpu/online")?;
+        let cpus = parse_cpu_list(&list)?;
+        Ok(cpus.into_iter().map(Cpu).collect())
+    }
+}
+
+// Parses the CPU list format used by sysfs (e.g., `0-3,5,7-8`).
+pub(crate) fn parse_cpu_list(list: &str) -> Result<Vec<u32>> {
+    let invalid = || {
+        let error = format!("Invalid CPU list `{}`", list.trim());
+        Error::new(ErrorKind::InvalidData, error)
+    };
+    let mut cpus = vec![];
+    for range in list.trim().split(',').filter(|it| !it.is_empty()) {
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let start: u32 = start.parse().map_err(|_| invalid())?;
+        let end: u32 = end.parse().map_err(|_| invalid())?;
+        if start > end {
+            return Err(invalid());
+        }
+        cpus.extend(start..=end);
+    }
+    Ok(cpus)
 }
 
 /// Which process (thread) to monitor.
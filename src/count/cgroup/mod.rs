@@ -0,0 +1,316 @@
+#[cfg(test)]
+mod test;
+
+use std::borrow::Borrow;
+use std::fs::{self, File};
+use std::io::{Error, ErrorKind, Result};
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use super::set::{CounterSet, SetStat};
+use crate::config::{sibling, Cgroup, Opts};
+use crate::event::Event;
+
+/// Counters of a cgroup on every online CPU.
+///
+/// Cgroup monitoring requires a concrete CPU, this type opens the event
+/// for the cgroup on every online CPU with [`CounterSet`] and sums them up.
+///
+/// Only cgroup v2 is supported. Events of tasks in descendant cgroups
+/// are also counted.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use perf_event_open::config::Opts;
+/// use perf_event_open::count::cgroup::CgroupCounter;
+/// use perf_event_open::event::sw::Software;
+///
+/// // Path relative to the cgroup v2 mount point, as shown in `/proc/<pid>/cgroup`.
+/// let counter = CgroupCounter::new("/", Software::TaskClock, Opts::default()).unwrap();
+///
+/// counter.enable().unwrap();
+/// thread::sleep(Duration::from_millis(100));
+/// counter.disable().unwrap();
+///
+/// let stat = counter.stat().unwrap();
+/// println!("{}: {} ns", counter.path().display(), stat.sum.count);
+/// ```
+pub struct CgroupCounter {
+    path: PathBuf,
+    set: CounterSet,
+}
+
+impl CgroupCounter {
+    /// Opens the event for the cgroup on every online CPU.
+    ///
+    /// The path is either a path in the cgroup v2 filesystem, or a path relative
+    /// to its mount point (e.g., `/system.slice/docker-<id>.scope`).
+    pub fn new(
+        path: impl AsRef<Path>,
+        event: impl TryInto<Event, Error = Error>,
+        opts: impl Borrow<Opts>,
+    ) -> Result<Self> {
+        let path = resolve(path.as_ref())?;
+        Self::open(path, event, opts)
+    }
+
+    /// Opens the event for the cgroup with the cgroup ID on every online CPU.
+    ///
+    /// The cgroup ID is the inode number of the cgroup directory, which is also
+    /// used by [`Sample::cgroup`][crate::sample::record::sample::Sample::cgroup].
+    pub fn from_id(
+        id: u64,
+        event: impl TryInto<Event, Error = Error>,
+        opts: impl Borrow<Opts>,
+    ) -> Result<Self> {
+        let root = mount_point()?;
+        let Some(path) = find_id(&root, id)? else {
+            let error = format!("Cgroup with ID {} not found", id);
+            return Err(Error::new(ErrorKind::NotFound, error));
+        };
+        Self::open(path, event, opts)
+    }
+
+    fn open(
+        path: PathBuf,
+        event: impl TryInto<Event, Error = Error>,
+        opts: impl Borrow<Opts>,
+    ) -> Result<Self> {
+        let cgroup = File::open(&path)?;
+        let target = |cpu| (Cgroup(&cgroup), cpu).into();
+        let set = CounterSet::with_target(event, target, opts)?;
+        Ok(Self { path, set })
+    }
+
+    /// Returns the path of the cgroup directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the counters on every CPU.
+    pub fn set(&self) -> &CounterSet {
+        &self.set
+    }
+
+    /// Adds sibling event to the group on every CPU.
+    ///
+    /// See [`CounterSet::add`] for details.
+    pub fn add(
+        &mut self,
+        event: impl TryInto<Event, Error = Error>,
+        opts: impl Borrow<sibling::Opts>,
+    ) -> Result<()> {
+        self.set.add(event, opts)
+    }
+
+    /// Enables counters on all CPUs.
+    pub fn enable(&self) -> Result<()> {
+        self.set.enable()
+    }
+
+    /// Disables counters on all CPUs.
+    pub fn disable(&self) -> Result<()> {
+        self.set.disable()
+    }
+
+    /// Clears the counts of counters on all CPUs.
+    pub fn clear_count(&self) -> Result<()> {
+        self.set.clear_count()
+    }
+
+    /// Returns the per-CPU and summed statistics.
+    pub fn stat(&self) -> Result<SetStat> {
+        self.set.stat()
+    }
+}
+
+/// Counters of every cgroup in a cgroup subtree.
+///
+/// Since events of descendant cgroups are also counted by a cgroup,
+/// the count of a cgroup includes the counts of its children.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use perf_event_open::config::Opts;
+/// use perf_event_open::count::cgroup::CgroupTree;
+/// use perf_event_open::event::sw::Software;
+///
+/// let tree = CgroupTree::new("/", Software::TaskClock, Opts::default()).unwrap();
+///
+/// tree.enable().unwrap();
+/// thread::sleep(Duration::from_millis(100));
+/// tree.disable().unwrap();
+///
+/// for (path, stat) in tree.stat().unwrap() {
+///     println!("{}: {} ns", path.display(), stat.sum.count);
+/// }
+/// ```
+pub struct CgroupTree {
+    cgroups: Vec<CgroupCounter>,
+}
+
+impl CgroupTree {
+    /// Opens the event for the cgroup and all its descendants on every online CPU.
+    ///
+    /// See [`CgroupCounter::new`] for the path format. Cgroups removed
+    /// while walking the subtree are skipped.
+    pub fn new(
+        path: impl AsRef<Path>,
+        event: impl TryInto<Event, Error = Error>,
+        opts: impl Borrow<Opts>,
+    ) -> Result<Self> {
+        let root = resolve(path.as_ref())?;
+        let event = event.try_into()?;
+        let opts = opts.borrow();
+
+        let mut cgroups = vec![];
+        for path in walk(&root)? {
+            match CgroupCounter::open(path, &event, opts) {
+                Ok(it) => cgroups.push(it),
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(Self { cgroups })
+    }
+
+    /// Returns the cgroup counters, with the root cgroup first and
+    /// every cgroup before its descendants.
+    pub fn cgroups(&self) -> &[CgroupCounter] {
+        &self.cgroups
+    }
+
+    /// Adds sibling event to the groups of all cgroups.
+    pub fn add(
+        &mut self,
+        event: impl TryInto<Event, Error = Error>,
+        opts: impl Borrow<sibling::Opts>,
+    ) -> Result<()> {
+        let event = event.try_into()?;
+        let opts = opts.borrow();
+        self.cgroups
+            .iter_mut()
+            .try_for_each(|it| it.add(&event, opts))
+    }
+
+    /// Enables counters of all cgroups.
+    pub fn enable(&self) -> Result<()> {
+        self.cgroups.iter().try_for_each(CgroupCounter::enable)
+    }
+
+    /// Disables counters of all cgroups.
+    pub fn disable(&self) -> Result<()> {
+        self.cgroups.iter().try_for_each(CgroupCounter::disable)
+    }
+
+    /// Clears the counts of counters of all cgroups.
+    pub fn clear_count(&self) -> Result<()> {
+        self.cgroups.iter().try_for_each(CgroupCounter::clear_count)
+    }
+
+    /// Returns the statistics by cgroup path.
+    pub fn stat(&self) -> Result<Vec<(PathBuf, SetStat)>> {
+        self.cgroups
+            .iter()
+            .map(|it| Ok((it.path.clone(), it.stat()?)))
+            .collect()
+    }
+}
+
+// Mount point of the cgroup v2 filesystem in `/proc/self/mountinfo`:
+// https://man7.org/linux/man-pages/man5/proc_pid_mountinfo.5.html
+fn mount_point() -> Result<PathBuf> {
+    let mountinfo = fs::read_to_string("/proc/self/mountinfo")?;
+    match parse_mountinfo(&mountinfo) {
+        Some(it) => Ok(it),
+        None => {
+            let error = "cgroup v2 filesystem is not mounted";
+            Err(Error::new(ErrorKind::NotFound, error))
+        }
+    }
+}
+
+fn parse_mountinfo(mountinfo: &str) -> Option<PathBuf> {
+    mountinfo.lines().find_map(|line| {
+        // 36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue
+        let (mount, fs) = line.split_once(" - ")?;
+        let mount_point = mount.split(' ').nth(4)?;
+        (fs.split(' ').next()? == "cgroup2").then(|| PathBuf::from(unescape(mount_point)))
+    })
+}
+
+// Spaces, tabs, newlines and backslashes are escaped as octal in mountinfo.
+fn unescape(field: &str) -> String {
+    let mut result = String::new();
+    let mut rest = field;
+    while let Some(i) = rest.find('\\') {
+        result.push_str(&rest[..i]);
+        let code = rest.get(i + 1..i + 4);
+        match code.and_then(|it| u8::from_str_radix(it, 8).ok()) {
+            Some(c) => {
+                result.push(c as char);
+                rest = &rest[i + 4..];
+            }
+            None => {
+                result.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn resolve(path: &Path) -> Result<PathBuf> {
+    let root = mount_point()?;
+    if path.starts_with(&root) {
+        return Ok(path.to_path_buf());
+    }
+    let relative = path.strip_prefix("/").unwrap_or(path);
+    Ok(root.join(relative))
+}
+
+fn find_id(root: &Path, id: u64) -> Result<Option<PathBuf>> {
+    for path in walk(root)? {
+        match fs::metadata(&path) {
+            Ok(it) if it.ino() == id => return Ok(Some(path)),
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(None)
+}
+
+// Cgroup directories in the subtree in pre-order.
+fn walk(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(path) = stack.pop() {
+        let entries = match fs::read_dir(&path) {
+            Ok(it) => it,
+            // The cgroup was removed.
+            Err(e) if e.kind() == ErrorKind::NotFound && !paths.is_empty() => continue,
+            Err(e) => return Err(e),
+        };
+        let mut children = vec![];
+        for entry in entries {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                children.push(entry.path());
+            }
+        }
+        children.sort();
+        stack.extend(children.into_iter().rev());
+        paths.push(path);
+    }
+    Ok(paths)
+}
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+use super::{parse_mountinfo, unescape, CgroupCounter, CgroupTree};
+use crate::config::Opts;
+use crate::event::sw::Software;
+use crate::test::busy_loop;
+
+#[test]
+fn test_parse_mountinfo() {
+    let mountinfo = "\
+24 30 0:22 / /sys rw,nosuid,nodev,noexec,relatime shared:7 - sysfs sysfs rw
+35 24 0:30 / /sys/fs/cgroup rw,nosuid,nodev,noexec,relatime shared:9 - cgroup2 cgroup2 rw,nsdelegate
+";
+    assert_eq!(
+        parse_mountinfo(mountinfo),
+        Some(PathBuf::from("/sys/fs/cgroup"))
+    );
+
+    let mountinfo = "35 24 0:30 / /mnt/my\\040cgroup rw - cgroup2 none rw\n";
+    assert_eq!(
+        parse_mountinfo(mountinfo),
+        Some(PathBuf::from("/mnt/my cgroup"))
+    );
+
+    let mountinfo = "36 24 0:31 / /sys/fs/cgroup/cpu rw - cgroup cgroup rw,cpu\n";
+    assert_eq!(parse_mountinfo(mountinfo), None);
+
+    assert_eq!(unescape("a\\011b\\134c\\"), "a\tb\\c\\");
+}
+
+#[test]
+fn test_cgroup() {
+    let counter = match CgroupCounter::new("/", Software::TaskClock, Opts::default()) {
+        Ok(it) => it,
+        // cgroup v2 is not mounted.
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+        Err(e) => panic!("{}", e),
+    };
+    assert!(counter.path().is_absolute());
+
+    let id = std::fs::metadata(counter.path()).unwrap();
+    let from_id = CgroupCounter::from_id(
+        std::os::unix::fs::MetadataExt::ino(&id),
+        Software::TaskClock,
+        Opts::default(),
+    )
+    .unwrap();
+    assert_eq!(from_id.path(), counter.path());
+
+    counter.enable().unwrap();
+    busy_loop();
+    counter.disable().unwrap();
+    assert!(counter.stat().unwrap().sum.count > 0);
+
+    let result = CgroupCounter::from_id(u64::MAX, Software::TaskClock, Opts::default());
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_tree() {
+    let tree = match CgroupTree::new("/", Software::TaskClock, Opts::default()) {
+        Ok(it) => it,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+        Err(e) => panic!("{}", e),
+    };
+    let root = tree.cgroups()[0].path();
+    assert!(tree.cgroups().iter().all(|it| it.path().starts_with(root)));
+    assert_eq!(tree.stat().unwrap().len(), tree.cgroups().len());
+}
@@ -0,0 +1,209 @@
+#[cfg(test)]
+mod test;
+
+use std::borrow::Borrow;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, ExitStatus};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::{Counter, Stat};
+use crate::config::{Cpu, Inherit, OnExecve, Opts, Proc};
+use crate::event::Event;
+
+/// Spawns the command and calls `attach` with its process ID before the command is executed.
+///
+/// The child process is stopped right before [`execve`](https://man7.org/linux/man-pages/man2/execve.2.html)
+/// until `attach` returns, so counters created by `attach` with [`exec_opts`] start counting
+/// from the first instruction of the command, and samplers created by `attach` don't miss any
+/// samples. The child process exits without executing the command if `attach` fails.
+///
+/// A pre-exec hook is registered to the command, so the command should not be spawned again.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::process::Command;
+///
+/// use perf_event_open::config::{Cpu, Opts, SampleOn};
+/// use perf_event_open::count::command::{exec_opts, spawn};
+/// use perf_event_open::count::Counter;
+/// use perf_event_open::event::sw::Software;
+///
+/// let mut opts = Opts::default();
+/// opts.sample_on = SampleOn::Freq(1000);
+/// let mut opts = exec_opts(opts);
+/// // Inherited counters on all CPUs can't be sampled.
+/// opts.inherit = None;
+///
+/// let mut command = Command::new("true");
+/// let (mut child, (counter, sampler)) = spawn(&mut command, |pid| {
+///     let counter = Counter::new(Software::TaskClock, (pid, Cpu::ALL), &opts)?;
+///     let sampler = counter.sampler(5)?;
+///     Ok((counter, sampler))
+/// })
+/// .unwrap();
+///
+/// child.wait().unwrap();
+/// println!("{} ns", counter.stat().unwrap().count);
+/// for it in sampler.iter() {
+///     println!("{:-?}", it);
+/// }
+/// ```
+pub fn spawn<T>(
+    command: &mut Command,
+    attach: impl FnOnce(Proc) -> Result<T>,
+) -> Result<(Child, T)> {
+    // The child reports its PID through `pid`, then waits on `go` before executing the command.
+    let (mut pid_r, pid_w) = pipe()?;
+    let (go_r, go_w) = pipe()?;
+    let (pid_w_fd, go_r_fd, go_w_fd) = (pid_w.as_raw_fd(), go_r.as_raw_fd(), go_w.as_raw_fd());
+
+    let pre_exec = move || {
+        // Only async-signal-safe functions can be called after `fork`.
+        unsafe {
+            // Close the write end of `go` inherited from the parent, so that
+            // reading `go` returns EOF if the parent fails to attach.
+            libc::close(go_w_fd);
+
+            let pid = libc::getpid().to_ne_bytes();
+            if libc::write(pid_w_fd, pid.as_ptr() as _, pid.len()) != pid.len() as isize {
+                return Err(Error::last_os_error());
+            }
+            let mut buf = 0_u8;
+            loop {
+                match libc::read(go_r_fd, &mut buf as *mut u8 as _, 1) {
+                    1 => return Ok(()),
+                    -1 if Error::last_os_error().kind() == ErrorKind::Interrupted => continue,
+                    -1 => return Err(Error::last_os_error()),
+                    // Allocating is not async-signal-safe, so `Error::other` can't be used.
+                    _ => return Err(ErrorKind::BrokenPipe.into()),
+                }
+            }
+        }
+    };
+    unsafe { command.pre_exec(pre_exec) };
+
+    thread::scope(|scope| {
+        // `Command::spawn` returns after the command is executed,
+        // so it must be called in another thread.
+        let spawning = scope.spawn(move || {
+            let child = command.spawn();
+            // Unblocks reading `pid` if the child fails to be forked.
+            drop(pid_w);
+            child
+        });
+
+        let mut pid = [0; size_of::<libc::pid_t>()];
+        let attached = pid_r.read_exact(&mut pid).ok().map(|_| -> Result<T> {
+            let it = attach(Proc(libc::pid_t::from_ne_bytes(pid) as _))?;
+            (&go_w).write_all(&[1])?;
+            Ok(it)
+        });
+        // The child exits without executing the command if `go` is closed without writing.
+        drop(go_w);
+        let child = spawning.join().unwrap();
+        drop(go_r);
+
+        match attached {
+            Some(attached) => {
+                let attached = attached?;
+                Ok((child?, attached))
+            }
+            // The child failed before reporting its PID.
+            None => Err(child
+                .err()
+                .unwrap_or_else(|| ErrorKind::UnexpectedEof.into())),
+        }
+    })
+}
+
+/// Returns the options to count the command from [`execve`](https://man7.org/linux/man-pages/man2/execve.2.html)
+/// and to follow its child tasks.
+///
+/// This disables the counter until `execve`, and enables
+/// [`OnExecve::Enable`] and [`Inherit::NewChild`].
+pub fn exec_opts(opts: impl Borrow<Opts>) -> Opts {
+    let mut opts = opts.borrow().clone();
+    opts.enable = false;
+    opts.on_execve = Some(OnExecve::Enable);
+    opts.inherit = Some(Inherit::NewChild);
+    opts
+}
+
+/// Counts the events of the command from [`execve`](https://man7.org/linux/man-pages/man2/execve.2.html)
+/// to exit, similar to the `perf stat <command>` command.
+///
+/// Each event is counted by its own counter with [`exec_opts`].
+///
+/// # Examples
+///
+/// ```rust
+/// use std::process::Command;
+///
+/// use perf_event_open::config::Opts;
+/// use perf_event_open::count::command::stat;
+/// use perf_event_open::event::sw::Software;
+///
+/// let mut command = Command::new("ls");
+/// let events = [Software::TaskClock, Software::PageFault];
+/// let stat = stat(&mut command, events, Opts::default()).unwrap();
+///
+/// println!("{} in {:?}", stat.status, stat.elapsed);
+/// println!("{} ns, {} page faults", stat.stats[0].count, stat.stats[1].count);
+/// ```
+pub fn stat<E>(
+    command: &mut Command,
+    events: impl IntoIterator<Item = E>,
+    opts: impl Borrow<Opts>,
+) -> Result<CommandStat>
+where
+    E: TryInto<Event, Error = Error>,
+{
+    let events = events
+        .into_iter()
+        .map(TryInto::try_into)
+        .collect::<Result<Vec<_>>>()?;
+    let opts = exec_opts(opts);
+
+    let (mut child, counters) = spawn(command, |pid| {
+        events
+            .iter()
+            .map(|it| Counter::new(it, (pid, Cpu::ALL), &opts))
+            .collect::<Result<Vec<_>>>()
+    })?;
+    let start = Instant::now();
+    let status = child.wait()?;
+    let elapsed = start.elapsed();
+
+    let stats = counters.iter().map(Counter::stat).collect::<Result<_>>()?;
+    Ok(CommandStat {
+        status,
+        elapsed,
+        stats,
+    })
+}
+
+/// Statistics of a command returned by [`stat`].
+#[derive(Clone, Debug)]
+pub struct CommandStat {
+    /// Exit status of the command.
+    pub status: ExitStatus,
+
+    /// Wall-clock time from `execve` to exit.
+    pub elapsed: Duration,
+
+    /// Statistics of the events in order.
+    pub stats: Vec<Stat>,
+}
+
+fn pipe() -> Result<(File, File)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+        return Err(Error::last_os_error());
+    }
+    Ok(unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) })
+}
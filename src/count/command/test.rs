@@ -0,0 +1,64 @@
+use std::io::{Error, ErrorKind};
+use std::process::Command;
+
+use super::{exec_opts, spawn, stat};
+use crate::config::{Cpu, Opts};
+use crate::count::Counter;
+use crate::event::sw::Software;
+
+#[test]
+fn test_stat() {
+    let mut command = Command::new("sh");
+    command.args([
+        "-c",
+        "i=0; while [ $i -lt 1000 ]; do i=$((i+1)); done; exit 3",
+    ]);
+    let events = [Software::TaskClock, Software::TaskClock];
+    let stat = stat(&mut command, events, Opts::default()).unwrap();
+
+    assert_eq!(stat.status.code(), Some(3));
+    assert_eq!(stat.stats.len(), 2);
+    assert!(stat.stats[0].count > 0);
+    assert!(stat.stats[1].count > 0);
+}
+
+#[test]
+fn test_inherit() {
+    // Counts of child processes are collected when they exit.
+    let mut command = Command::new("sh");
+    command.args([
+        "-c",
+        "sh -c 'i=0; while [ $i -lt 1000 ]; do i=$((i+1)); done'",
+    ]);
+    let stat = stat(&mut command, [Software::TaskClock], Opts::default()).unwrap();
+    assert!(stat.status.success());
+    assert!(stat.stats[0].count > 0);
+}
+
+#[test]
+fn test_disabled_before_exec() {
+    let opts = exec_opts(Opts::default());
+    let mut command = Command::new("true");
+    let (mut child, counter) = spawn(&mut command, |pid| {
+        let counter = Counter::new(Software::TaskClock, (pid, Cpu::ALL), &opts)?;
+        // The child is stopped before exec.
+        assert_eq!(counter.stat()?.count, 0);
+        Ok(counter)
+    })
+    .unwrap();
+    assert!(child.wait().unwrap().success());
+    assert!(counter.stat().unwrap().count > 0);
+}
+
+#[test]
+fn test_errors() {
+    let mut command = Command::new("true");
+    let result = spawn(&mut command, |_| -> std::io::Result<()> {
+        Err(Error::other("attach"))
+    });
+    assert_eq!(result.unwrap_err().to_string(), "attach");
+
+    let mut command = Command::new("/nonexistent");
+    let result = spawn(&mut command, |_| Ok(()));
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::NotFound);
+}
@@ -0,0 +1,368 @@
+#[cfg(test)]
+mod test;
+
+use std::borrow::Borrow;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io::Error;
+
+use super::group::CounterGroup;
+use crate::config::{attr, sibling, Opts, Target};
+use crate::event::dp::Pmu;
+use crate::event::Event;
+use crate::ffi::{bindings as b, Attr};
+
+/// Diagnosis of [`perf_event_open`](https://man7.org/linux/man-pages/man2/perf_event_open.2.html) failures.
+///
+/// The errno alone hardly tells what went wrong, e.g., `EINVAL` may come from any
+/// invalid option. Errors returned by [`Counter::new`][super::Counter::new] and
+/// [`CounterGroup::add`][super::group::CounterGroup::add] can be diagnosed with
+/// [`OpenError::diagnose`] and [`OpenError::diagnose_sibling`] by inspecting the
+/// errno, the options and the system settings, with messages similar to the
+/// `evsel__open_strerror` function of perf.
+///
+/// # Examples
+///
+/// ```rust
+/// use perf_event_open::config::{Cpu, Opts, Proc};
+/// use perf_event_open::count::{Counter, OpenError};
+/// use perf_event_open::event::sw::Software;
+///
+/// let target = (Proc(i32::MAX as _), Cpu::ALL);
+/// let error = Counter::new(Software::TaskClock, target, Opts::default()).err().unwrap();
+/// assert_eq!(error.raw_os_error(), Some(libc::ESRCH));
+///
+/// let diagnosis = OpenError::diagnose(&error, Software::TaskClock, target, Opts::default());
+/// assert_eq!(diagnosis, Some(OpenError::NoProcess { pid: i32::MAX }));
+/// println!("{}", diagnosis.unwrap());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
+pub enum OpenError {
+    /// `EACCES` or `EPERM`: Access denied by `perf_event_paranoid` or missing capabilities.
+    ///
+    /// See [permission][super::Counter#permission] for details.
+    Permission {
+        /// Errno.
+        errno: i32,
+        /// Current `perf_event_paranoid` setting.
+        paranoid: Option<i32>,
+        /// Events happened in kernel space are monitored.
+        kernel: bool,
+        /// All processes on the CPU are monitored.
+        cpu_wide: bool,
+    },
+
+    /// `EOPNOTSUPP`: The PMU does not support the requested [`SampleSkid`][crate::config::SampleSkid].
+    PreciseIp {
+        /// Requested `precise_ip` level.
+        level: u8,
+        /// Max `precise_ip` level supported by the PMU, from `caps/max_precise` in sysfs.
+        max: Option<u8>,
+    },
+
+    /// `EOPNOTSUPP`: The event can't be sampled, since the PMU has no overflow interrupt.
+    NoInterrupt,
+
+    /// `EOPNOTSUPP`: Options not supported by the PMU, e.g., branch stack or excluding privilege levels.
+    NotSupported,
+
+    /// `ENOENT`: The event or the PMU does not exist.
+    NoEvent {
+        /// PMU type.
+        ty: u32,
+        /// Event config.
+        config: u64,
+        /// Name of the PMU in sysfs, `None` if no PMU has the type.
+        pmu: Option<String>,
+    },
+
+    /// `ENODEV`: The PMU is not available on the CPU, or the CPU is offline.
+    NoDevice {
+        /// Monitored CPU, -1 for all CPUs.
+        cpu: i32,
+    },
+
+    /// `ESRCH`: The monitored process does not exist.
+    NoProcess {
+        /// Monitored process ID.
+        pid: i32,
+    },
+
+    /// `EMFILE`: Too many open files, each counter takes a file descriptor.
+    TooManyFiles {
+        /// Current `RLIMIT_NOFILE` soft limit.
+        limit: Option<u64>,
+    },
+
+    /// `EBUSY`: Another event has exclusive access to the PMU.
+    Busy,
+
+    /// `EINVAL`: The sample frequency is higher than `perf_event_max_sample_rate`.
+    FreqTooHigh {
+        /// Requested frequency.
+        freq: u64,
+        /// Current `perf_event_max_sample_rate` setting.
+        max: u64,
+    },
+
+    /// `EOVERFLOW`: Max stack frames is higher than `perf_event_max_stack`.
+    StackTooDeep {
+        /// Requested max stack frames.
+        frames: u16,
+        /// Current `perf_event_max_stack` setting.
+        max: Option<u32>,
+    },
+
+    /// `EINVAL`: Invalid options, or options not supported by the PMU.
+    InvalidOpts,
+
+    /// Other errno.
+    Other(i32),
+}
+
+impl OpenError {
+    /// Diagnoses the error returned by [`Counter::new`][super::Counter::new]
+    /// with the same arguments.
+    ///
+    /// The system settings are read on each call. Returns `None` if the error
+    /// is not returned by `perf_event_open`.
+    pub fn diagnose(
+        error: &Error,
+        event: impl TryInto<Event, Error = Error>,
+        target: impl Into<Target>,
+        opts: impl Borrow<Opts>,
+    ) -> Option<Self> {
+        let errno = error.raw_os_error()?;
+        let Event(event_cfg) = event.try_into().ok()?;
+        let attr = attr::from(event_cfg, opts.borrow()).ok()?;
+        Some(Self::from_errno(errno, &attr, &target.into()))
+    }
+
+    /// Diagnoses the error returned by [`CounterGroup::add`] with the same arguments.
+    ///
+    /// See [`OpenError::diagnose`] for details.
+    pub fn diagnose_sibling(
+        error: &Error,
+        group: &CounterGroup,
+        event: impl TryInto<Event, Error = Error>,
+        opts: impl Borrow<sibling::Opts>,
+    ) -> Option<Self> {
+        let errno = error.raw_os_error()?;
+        let Event(event_cfg) = event.try_into().ok()?;
+        let leader = group.leader();
+        // We only change the attr fields related to event config,
+        // which are not used to initialize the sibling attr.
+        let leader_attr = unsafe { &*leader.attr.get() };
+        let attr = sibling::attr::from(event_cfg, opts.borrow(), leader_attr).ok()?;
+        Some(Self::from_errno(errno, &attr, &leader.target))
+    }
+
+    /// Returns the errno.
+    pub fn errno(&self) -> i32 {
+        match self {
+            Self::Permission { errno, .. } => *errno,
+            Self::PreciseIp { .. } | Self::NoInterrupt | Self::NotSupported => libc::EOPNOTSUPP,
+            Self::NoEvent { .. } => libc::ENOENT,
+            Self::NoDevice { .. } => libc::ENODEV,
+            Self::NoProcess { .. } => libc::ESRCH,
+            Self::TooManyFiles { .. } => libc::EMFILE,
+            Self::Busy => libc::EBUSY,
+            Self::FreqTooHigh { .. } | Self::InvalidOpts => libc::EINVAL,
+            Self::StackTooDeep { .. } => libc::EOVERFLOW,
+            Self::Other(errno) => *errno,
+        }
+    }
+
+    fn from_errno(errno: i32, attr: &Attr, target: &Target) -> Self {
+        match errno {
+            libc::EACCES | libc::EPERM => Self::Permission {
+                errno,
+                paranoid: read_sysctl("perf_event_paranoid"),
+                kernel: attr.exclude_kernel() == 0,
+                cpu_wide: target.pid == -1,
+            },
+            libc::EOPNOTSUPP if attr.precise_ip() > 0 => Self::PreciseIp {
+                level: attr.precise_ip() as _,
+                max: max_precise(attr.type_),
+            },
+            libc::EOPNOTSUPP if unsafe { attr.__bindgen_anon_1.sample_period } > 0 => {
+                Self::NoInterrupt
+            }
+            libc::EOPNOTSUPP => Self::NotSupported,
+            libc::ENOENT => Self::NoEvent {
+                ty: attr.type_,
+                config: attr.config,
+                pmu: pmu_name(attr.type_),
+            },
+            libc::ENODEV => Self::NoDevice { cpu: target.cpu },
+            // `pid` is the cgroup fd if `PERF_FLAG_PID_CGROUP` is set.
+            libc::ESRCH if target.flags & b::PERF_FLAG_PID_CGROUP as u64 == 0 => {
+                Self::NoProcess { pid: target.pid }
+            }
+            libc::EMFILE => Self::TooManyFiles { limit: nofile() },
+            libc::EBUSY => Self::Busy,
+            libc::EINVAL => {
+                let freq = unsafe { attr.__bindgen_anon_1.sample_freq };
+                match read_sysctl::<u64>("perf_event_max_sample_rate") {
+                    Some(max) if attr.freq() > 0 && freq > max => Self::FreqTooHigh { freq, max },
+                    _ => Self::InvalidOpts,
+                }
+            }
+            libc::EOVERFLOW => Self::StackTooDeep {
+                #[cfg(feature = "linux-4.8")]
+                frames: attr.sample_max_stack,
+                #[cfg(not(feature = "linux-4.8"))]
+                frames: 0,
+                max: read_sysctl("perf_event_max_stack"),
+            },
+            _ => Self::Other(errno),
+        }
+    }
+}
+
+impl Display for OpenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Permission {
+                paranoid,
+                kernel,
+                cpu_wide,
+                ..
+            } => {
+                write!(f, "Access to performance monitoring is limited")?;
+                match paranoid {
+                    Some(it) => write!(f, " by `perf_event_paranoid` (currently {})", it)?,
+                    None => write!(f, ", `perf_event_paranoid` is not available")?,
+                }
+                write!(f, ". Consider running with `CAP_PERFMON` or `CAP_SYS_ADMIN`, ")?;
+                write!(f, "or adjusting `/proc/sys/kernel/perf_event_paranoid`")?;
+                match paranoid {
+                    Some(it) if *it >= 2 && *kernel => {
+                        write!(f, " to 1, or excluding kernel with `Priv::kernel`")?;
+                    }
+                    Some(it) if *it >= 1 && *cpu_wide => {
+                        write!(f, " to 0, or monitoring a process instead of all processes")?;
+                    }
+                    Some(it) if *it >= 0 => write!(f, " to -1")?,
+                    _ => (),
+                }
+                write!(f, ".")
+            }
+            Self::PreciseIp { level, max } => {
+                write!(f, "The PMU does not support `precise_ip` level {}", level)?;
+                if let Some(max) = max {
+                    write!(f, " (max {})", max)?;
+                }
+                write!(f, ", try a larger `SampleSkid`.")
+            }
+            Self::NoInterrupt => write!(
+                f,
+                "The event does not support sampling, since the PMU has no overflow interrupt. \
+                Try counting instead of sampling."
+            ),
+            Self::NotSupported => write!(
+                f,
+                "Options not supported by the PMU, e.g., branch stack or excluding privilege levels."
+            ),
+            Self::NoEvent { ty, config, pmu } => match pmu {
+                Some(pmu) => write!(
+                    f,
+                    "The event (config {:#x}) is not supported by PMU `{}`.",
+                    config, pmu
+                ),
+                // Generic events are handled by the core PMU, which may be absent
+                // (e.g., in virtual machines).
+                None if *ty < b::PERF_TYPE_MAX => write!(
+                    f,
+                    "The generic event (type {}, config {:#x}) is not supported by the CPU.",
+                    ty, config
+                ),
+                None => write!(
+                    f,
+                    "No PMU with type {} found in `/sys/bus/event_source/devices`.",
+                    ty
+                ),
+            },
+            Self::NoDevice { cpu } if *cpu >= 0 => write!(
+                f,
+                "The PMU is not available on CPU {}, or the CPU is offline.",
+                cpu
+            ),
+            Self::NoDevice { .. } => write!(f, "The PMU is not available on the CPUs."),
+            Self::NoProcess { pid } => write!(f, "The process {} does not exist.", pid),
+            Self::TooManyFiles { limit } => {
+                write!(f, "Too many open files")?;
+                if let Some(limit) = limit {
+                    write!(f, " (limit {})", limit)?;
+                }
+                write!(
+                    f,
+                    ", each counter takes a file descriptor. Try raising the limit with `ulimit -n`, \
+                    or using fewer events or CPUs."
+                )
+            }
+            Self::Busy => write!(
+                f,
+                "The PMU is busy, another event may have exclusive access to it (e.g., a hardware tracer)."
+            ),
+            Self::FreqTooHigh { freq, max } => write!(
+                f,
+                "The sample frequency {} is higher than `perf_event_max_sample_rate` ({}), \
+                try a lower frequency or adjusting `/proc/sys/kernel/perf_event_max_sample_rate`.",
+                freq, max
+            ),
+            Self::StackTooDeep { frames, max } => {
+                write!(f, "Max stack frames {} is higher than `perf_event_max_stack`", frames)?;
+                if let Some(max) = max {
+                    write!(f, " ({})", max)?;
+                }
+                write!(
+                    f,
+                    ", try less frames or adjusting `/proc/sys/kernel/perf_event_max_stack`."
+                )
+            }
+            Self::InvalidOpts => write!(
+                f,
+                "Invalid options, or options not supported by the PMU or the running kernel. \
+                Use `KernelCaps::check` to find options rejected by the running kernel."
+            ),
+            Self::Other(errno) => Display::fmt(&Error::from_raw_os_error(*errno), f),
+        }
+    }
+}
+
+impl std::error::Error for OpenError {}
+
+fn read_sysctl<T: std::str::FromStr>(name: &str) -> Option<T> {
+    let path = format!("/proc/sys/kernel/{}", name);
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+fn pmu_name(ty: u32) -> Option<String> {
+    let types = Pmu::types().ok()?;
+    types.into_iter().find(|it| it.1 == ty).map(|it| it.0)
+}
+
+// Generic hardware events are handled by the core PMU, which reports `max_precise`.
+fn max_precise(ty: u32) -> Option<u8> {
+    let generic = matches!(
+        ty,
+        b::PERF_TYPE_HARDWARE | b::PERF_TYPE_HW_CACHE | b::PERF_TYPE_RAW
+    );
+    let types = Pmu::types().ok()?;
+    let max = types
+        .into_iter()
+        .filter(|it| generic || it.1 == ty)
+        .find_map(|it| Pmu::cap_of(&it.0, "max_precise").ok())?;
+    max.parse().ok()
+}
+
+fn nofile() -> Option<u64> {
+    let mut rlimit = unsafe { std::mem::zeroed::<libc::rlimit>() };
+    match unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut rlimit) } {
+        0 => Some(rlimit.rlim_cur as _),
+        _ => None,
+    }
+}
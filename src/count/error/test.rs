@@ -0,0 +1,94 @@
+use std::io::ErrorKind;
+
+use super::OpenError;
+use crate::config::{sibling, Cpu, Opts, Proc, SampleOn, SampleSkid, Target};
+use crate::count::group::CounterGroup;
+use crate::count::Counter;
+use crate::event::sw::Software;
+use crate::ffi::Attr;
+
+fn target() -> Target {
+    (Proc::CURRENT, Cpu::ALL).into()
+}
+
+#[test]
+fn test_no_process() {
+    let target = (Proc(i32::MAX as _), Cpu::ALL);
+    let error = Counter::new(Software::TaskClock, target, Opts::default())
+        .err()
+        .unwrap();
+    assert_eq!(error.raw_os_error(), Some(libc::ESRCH));
+    let open_error = OpenError::diagnose(&error, Software::TaskClock, target, Opts::default());
+    assert_eq!(open_error, Some(OpenError::NoProcess { pid: i32::MAX }));
+
+    // Errors not returned by `perf_event_open`.
+    let error = std::io::Error::other("other");
+    let open_error = OpenError::diagnose(&error, Software::TaskClock, target, Opts::default());
+    assert_eq!(open_error, None);
+}
+
+#[test]
+fn test_freq_too_high() {
+    let opts = Opts {
+        sample_on: SampleOn::Freq(u64::MAX >> 1),
+        ..Default::default()
+    };
+    let error = Counter::new(Software::TaskClock, target(), &opts)
+        .err()
+        .unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    let open_error = OpenError::diagnose(&error, Software::TaskClock, target(), &opts).unwrap();
+    assert!(matches!(open_error, OpenError::FreqTooHigh { .. }));
+    assert_eq!(open_error.errno(), libc::EINVAL);
+}
+
+#[test]
+fn test_diagnose_sibling() {
+    let Ok(leader) = Counter::new(Software::TaskClock, target(), Opts::default()) else {
+        return;
+    };
+    let mut group = CounterGroup::from(leader);
+    let opts = sibling::Opts {
+        sample_on: SampleOn::Freq(u64::MAX >> 1),
+        ..Default::default()
+    };
+    let error = group.add(Software::TaskClock, &opts).err().unwrap();
+    let open_error = OpenError::diagnose_sibling(&error, &group, Software::TaskClock, &opts);
+    assert!(matches!(open_error, Some(OpenError::FreqTooHigh { .. })));
+}
+
+#[test]
+fn test_diagnose() {
+    let mut attr = Attr::default();
+    attr.set_precise_ip(SampleSkid::Zero.as_precise_ip() as _);
+    let error = OpenError::from_errno(libc::EOPNOTSUPP, &attr, &target());
+    assert!(matches!(error, OpenError::PreciseIp { level: 3, .. }));
+
+    attr.set_precise_ip(0);
+    attr.__bindgen_anon_1.sample_period = 1;
+    let error = OpenError::from_errno(libc::EOPNOTSUPP, &attr, &target());
+    assert_eq!(error, OpenError::NoInterrupt);
+
+    attr.type_ = u32::MAX;
+    let error = OpenError::from_errno(libc::ENOENT, &attr, &target());
+    assert!(matches!(error, OpenError::NoEvent { pmu: None, .. }));
+
+    let target = (Proc::ALL, Cpu(0)).into();
+    let error = OpenError::from_errno(libc::EACCES, &attr, &target);
+    assert!(matches!(
+        error,
+        OpenError::Permission { cpu_wide: true, .. }
+    ));
+    assert!(error.to_string().contains("perf_event_paranoid"));
+
+    let error = OpenError::from_errno(libc::EMFILE, &attr, &target);
+    assert!(matches!(error, OpenError::TooManyFiles { limit: Some(_) }));
+
+    let error = OpenError::from_errno(libc::ENOSPC, &attr, &target);
+    assert_eq!(error, OpenError::Other(libc::ENOSPC));
+    assert_eq!(error.errno(), libc::ENOSPC);
+    assert_eq!(
+        error.to_string(),
+        std::io::Error::from_raw_os_error(libc::ENOSPC).to_string()
+    );
+}
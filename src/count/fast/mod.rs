@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod test;
+
+use std::io::{Error, ErrorKind, Result};
+use std::ptr::{addr_of, read_volatile};
+use std::sync::atomic::{compiler_fence, Ordering};
+use std::sync::Arc;
+
+use super::Counter;
+use crate::ffi::{bindings as b, Metadata, PAGE_SIZE};
+use crate::sample::arena::Arena;
+use crate::sample::time::TscConv;
+
+// `cap_user_rdpmc` in `perf_event_mmap_page`:
+// https://github.com/torvalds/linux/blob/v6.13/include/uapi/linux/perf_event.h
+const CAP_USER_RDPMC: u64 = 1 << 2;
+
+/// Self-monitoring counter reader.
+///
+/// This reader maps the metadata page of the counter without a data buffer. If the
+/// kernel allows reading hardware counters in user space (`cap_user_rdpmc`), the count
+/// is read with the `rdpmc` instruction on x86, or the PMU system registers on arm64,
+/// under the seqlock of the metadata page, which is much cheaper than the `read`
+/// system call. Otherwise, it falls back to the `read` system call.
+///
+/// The hardware counter can only be read on the CPU that runs the monitored thread,
+/// so the counter must monitor the calling thread with `(Proc::CURRENT, Cpu::ALL)`,
+/// and this type is not `Send`. Creating the reader for counters of other threads or
+/// CPUs results in [`ErrorKind::InvalidInput`], they should use [`Counter::stat`] instead.
+///
+/// Userspace access is controlled by `/sys/bus/event_source/devices/cpu/rdpmc` on x86.
+/// On arm64 it needs `sysctl kernel.perf_user_access=1` and the event must be opened
+/// with the `rdpmc` format term of the PMU (`config1:1`).
+///
+/// # Examples
+///
+/// ```rust
+/// use perf_event_open::config::{Cpu, Opts, Proc};
+/// use perf_event_open::count::Counter;
+/// use perf_event_open::event::hw::Hardware;
+///
+/// let target = (Proc::CURRENT, Cpu::ALL);
+/// let counter = Counter::new(Hardware::Instr, target, Opts::default()).unwrap();
+/// let reader = counter.fast_reader().unwrap();
+///
+/// counter.enable().unwrap();
+/// let before = reader.count().unwrap();
+/// std::hint::black_box((0..1000).sum::<usize>());
+/// let after = reader.count().unwrap();
+///
+/// println!("{} instructions (fast path: {})", after - before, reader.is_fast());
+/// ```
+pub struct FastReader<'a> {
+    counter: &'a Counter,
+    arena: Arena,
+}
+
+impl<'a> FastReader<'a> {
+    pub(super) fn new(counter: &'a Counter) -> Result<Self> {
+        if Arc::strong_count(&counter.perf) != 1 {
+            // The metadata page is shared with the sampler, which requires the same mmap length.
+            let error = "There is already a sampler attached to this counter.";
+            return Err(Error::new(ErrorKind::AlreadyExists, error));
+        }
+        // Other targets may be scheduled on another CPU, the index in the metadata page
+        // would then point to an unrelated hardware counter of the calling CPU.
+        let target = &counter.target;
+        let cgroup = target.flags & b::PERF_FLAG_PID_CGROUP as u64 > 0;
+        if target.pid != 0 || target.cpu != -1 || cgroup {
+            let error = "The counter must monitor `(Proc::CURRENT, Cpu::ALL)`.";
+            return Err(Error::new(ErrorKind::InvalidInput, error));
+        }
+        let arena = Arena::new(&counter.perf, *PAGE_SIZE, 0)?;
+        Ok(Self { counter, arena })
+    }
+
+    fn metadata(&self) -> *const Metadata {
+        self.arena.as_slice().as_ptr() as *const Metadata
+    }
+
+    /// Returns `true` if the kernel allows reading the counter in user space.
+    ///
+    /// The count is still read with the `read` system call if the event is
+    /// not a hardware event or not currently scheduled on the PMU.
+    pub fn is_fast(&self) -> bool {
+        let metadata = self.metadata();
+        let caps = unsafe { read_volatile(addr_of!((*metadata).__bindgen_anon_1.capabilities)) };
+        caps & CAP_USER_RDPMC > 0 && cfg!(any(target_arch = "x86_64", target_arch = "aarch64"))
+    }
+
+    /// Returns the event count.
+    ///
+    /// This is the same as [`Stat::count`][super::Stat::count] returned by [`Counter::stat`].
+    pub fn count(&self) -> Result<u64> {
+        match self.read_pmc() {
+            Some(count) => Ok(count),
+            None => Ok(self.counter.stat()?.count),
+        }
+    }
+
+    /// Returns the conversion between hardware timestamps and perf time.
+    ///
+    /// Returns `None` if the kernel does not support it, see [`TscConv`] for details.
+    pub fn tsc_conv(&self) -> Option<TscConv> {
+        TscConv::from_metadata(self.metadata())
+    }
+
+    // Same as the example in the comments of `perf_event_mmap_page::index`:
+    // https://github.com/torvalds/linux/blob/v6.13/include/uapi/linux/perf_event.h
+    fn read_pmc(&self) -> Option<u64> {
+        let metadata = self.metadata();
+        macro_rules! field {
+            ($field:ident) => {
+                unsafe { read_volatile(addr_of!((*metadata).$field)) }
+            };
+        }
+
+        loop {
+            let seq = field!(lock);
+            compiler_fence(Ordering::SeqCst);
+
+            let caps =
+                unsafe { read_volatile(addr_of!((*metadata).__bindgen_anon_1.capabilities)) };
+            let index = field!(index);
+            let mut count = field!(offset);
+            if caps & CAP_USER_RDPMC == 0 || index == 0 {
+                return None;
+            }
+            let width = field!(pmc_width) as u32;
+            if width == 0 || width > 64 {
+                return None;
+            }
+            let pmc = unsafe { rdpmc(index - 1)? };
+            // Sign extend the counter value to 64 bits.
+            let pmc = ((pmc << (64 - width)) as i64) >> (64 - width);
+            count = count.wrapping_add(pmc);
+
+            compiler_fence(Ordering::SeqCst);
+            if field!(lock) == seq {
+                return Some(count as u64);
+            }
+        }
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+unsafe fn rdpmc(counter: u32) -> Option<u64> {
+    let (lo, hi): (u32, u32);
+    std::arch::asm!(
+        "rdpmc",
+        in("ecx") counter,
+        out("eax") lo,
+        out("edx") hi,
+        options(nostack, nomem, preserves_flags),
+    );
+    Some((hi as u64) << 32 | lo as u64)
+}
+
+// Same as `read_perf_counter` in libperf:
+// https://github.com/torvalds/linux/blob/v6.13/tools/lib/perf/mmap.c
+#[cfg(target_arch = "aarch64")]
+unsafe fn rdpmc(counter: u32) -> Option<u64> {
+    const CYCLE_COUNTER: u32 = 31;
+
+    let val: u64;
+    if counter == CYCLE_COUNTER {
+        std::arch::asm!("mrs {}, pmccntr_el0", out(reg) val, options(nostack, nomem));
+    } else {
+        std::arch::asm!(
+            "msr pmselr_el0, {0}",
+            "isb",
+            "mrs {1}, pmxevcntr_el0",
+            in(reg) counter as u64,
+            out(reg) val,
+            options(nostack, nomem),
+        );
+    }
+    Some(val)
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+unsafe fn rdpmc(_: u32) -> Option<u64> {
+    None
+}
@@ -0,0 +1,62 @@
+use crate::config::{Cpu, Opts, Proc};
+use crate::count::Counter;
+use crate::event::hw::Hardware;
+use crate::event::sw::Software;
+use crate::test::busy_loop;
+
+#[test]
+fn test_fallback() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let counter = Counter::new(Software::TaskClock, target, Opts::default()).unwrap();
+    let reader = counter.fast_reader().unwrap();
+
+    counter.enable().unwrap();
+    busy_loop();
+    counter.disable().unwrap();
+
+    // Software events are never read by `rdpmc`.
+    let count = reader.count().unwrap();
+    assert!(count > 0);
+    assert_eq!(count, counter.stat().unwrap().count);
+}
+
+#[test]
+fn test_sampler_conflict() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let counter = Counter::new(Software::TaskClock, target, Opts::default()).unwrap();
+    let sampler = counter.sampler(0).unwrap();
+    let error = counter.fast_reader().err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::AlreadyExists);
+    drop(sampler);
+    assert!(counter.fast_reader().is_ok());
+}
+
+#[test]
+fn test_hardware() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let Ok(counter) = Counter::new(Hardware::Instr, target, Opts::default()) else {
+        return; // No hardware PMU.
+    };
+    let reader = counter.fast_reader().unwrap();
+
+    counter.enable().unwrap();
+    let before = reader.count().unwrap();
+    busy_loop();
+    let after = reader.count().unwrap();
+    counter.disable().unwrap();
+
+    assert!(after > before);
+    assert!(counter.stat().unwrap().count >= after);
+}
+
+#[test]
+fn test_other_target() {
+    let opts = Opts::default();
+    let counter = Counter::new(Software::TaskClock, (Proc::CURRENT, Cpu(0)), &opts).unwrap();
+    let error = counter.fast_reader().err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+
+    let counter = Counter::new(Software::TaskClock, (Proc::ALL, Cpu(0)), &opts).unwrap();
+    let error = counter.fast_reader().err().unwrap();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidInput);
+}
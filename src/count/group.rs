@@ -6,7 +6,7 @@ use std::os::fd::AsRawFd;
 use std::rc::Rc;
 use std::sync::Arc;
 
-use super::{Counter, Stat};
+use super::{ioctl, Counter, Stat};
 use crate::config::sibling::attr::from;
 use crate::config::sibling::Opts;
 use crate::event::Event;
@@ -96,6 +96,9 @@ impl CounterGroup {
     /// Add sibling event to group.
     ///
     /// All siblings share the same [target][crate::config::Target] with the group leader.
+    ///
+    /// Errors returned by `perf_event_open` can be diagnosed with
+    /// [`OpenError::diagnose_sibling`][super::OpenError::diagnose_sibling].
     pub fn add(
         &mut self,
         event: impl TryInto<Event, Error = io::Error>,
@@ -103,11 +106,13 @@ impl CounterGroup {
     ) -> Result<Rc<Counter>> {
         let leader = &self.leader;
 
+        let Event(mut event_cfg) = event.try_into()?;
+        let keep_alive = event_cfg.keep_alive.take();
         let attr = {
             // We only change the attr fields related to event config,
             // which are not used to initialize the sibling attr.
             let leader_attr = unsafe { &*leader.attr.get() };
-            from(event.try_into()?.0, opts.borrow(), leader_attr)?
+            from(event_cfg, opts.borrow(), leader_attr)?
         };
         let group_fd = leader.perf.as_raw_fd();
         // All events in a group should monitor the same task (or cgroup) and CPU:
@@ -132,6 +137,7 @@ impl CounterGroup {
             attr: UnsafeCell::new(attr),
             perf: Arc::new(perf),
             read_buf: UnsafeCell::new(read_buf),
+            keep_alive: UnsafeCell::new(keep_alive),
         });
 
         self.siblings.push(Rc::clone(&sibling));
@@ -159,37 +165,16 @@ impl CounterGroup {
 
     /// Enables all counters in the group.
     pub fn enable(&self) -> Result<()> {
-        syscall!(
-            unsafe,
-            ioctl_arg,
-            &self.leader.perf,
-            b::PERF_IOC_OP_ENABLE as u64,
-            b::PERF_IOC_FLAG_GROUP as u64,
-        )?;
-        Ok(())
+        ioctl::enable(&self.leader.perf, true)
     }
 
     /// Disables all counters in the group.
     pub fn disable(&self) -> Result<()> {
-        syscall!(
-            unsafe,
-            ioctl_arg,
-            &self.leader.perf,
-            b::PERF_IOC_OP_DISABLE as u64,
-            b::PERF_IOC_FLAG_GROUP as u64,
-        )?;
-        Ok(())
+        ioctl::disable(&self.leader.perf, true)
     }
 
     /// Clears the counts of all counters in the group.
     pub fn clear_count(&self) -> Result<()> {
-        syscall!(
-            unsafe,
-            ioctl_arg,
-            &self.leader.perf,
-            b::PERF_IOC_OP_RESET as u64,
-            b::PERF_IOC_FLAG_GROUP as u64,
-        )?;
-        Ok(())
+        ioctl::reset(&self.leader.perf, true)
     }
 }
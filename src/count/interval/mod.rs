@@ -0,0 +1,323 @@
+#[cfg(test)]
+mod test;
+
+use std::fs::File;
+use std::io::Result;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use futures::channel::mpsc::{self, UnboundedReceiver};
+use futures::{Stream, StreamExt};
+
+use super::group::CounterGroup;
+use super::{Counter, Stat};
+use crate::ffi::syscall;
+
+/// Reads counters periodically, similar to the `perf stat -I` command.
+///
+/// Each [interval stat][IntervalStat] contains the count deltas of the counters
+/// since the previous read, scaled by the enabled and running time deltas
+/// to estimate the totals if multiplexing is happening.
+///
+/// Counters should have [`time_enabled`][crate::config::StatFormat::time_enabled] and
+/// [`time_running`][crate::config::StatFormat::time_running] in the stat format, or
+/// the counts are not scaled.
+///
+/// The reader reads duplicated handles of the counters, so it can be sent to
+/// other threads even for [`CounterGroup`], and the counters can still be used
+/// (e.g., enabled or disabled) while the reader is running.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// use perf_event_open::config::{Cpu, Opts, Proc};
+/// use perf_event_open::count::{Counter, IntervalReader};
+/// use perf_event_open::event::sw::Software;
+///
+/// let target = (Proc::CURRENT, Cpu::ALL);
+///
+/// let mut opts = Opts::default();
+/// opts.stat_format.time_enabled = true;
+/// opts.stat_format.time_running = true;
+///
+/// let counter = Counter::new(Software::TaskClock, target, opts).unwrap();
+/// counter.enable().unwrap();
+///
+/// let mut reader = IntervalReader::new(Duration::from_millis(10));
+/// reader.add(&counter).unwrap();
+///
+/// for _ in 0..3 {
+///     let stat = reader.wait().unwrap();
+///     println!("{:?}: {} ns", stat.elapsed, stat.stats[0].scaled);
+/// }
+/// ```
+pub struct IntervalReader {
+    interval: Duration,
+    start: Instant,
+    ticks: u32,
+    last: Instant,
+    sources: Vec<Source>,
+}
+
+struct Source {
+    file: File,
+    read_format: u64,
+    read_buf: Vec<u8>,
+    prev: Stat,
+}
+
+impl IntervalReader {
+    /// Creates a reader that reads every `interval`.
+    pub fn new(interval: Duration) -> Self {
+        let now = Instant::now();
+        Self {
+            interval,
+            start: now,
+            ticks: 0,
+            last: now,
+            sources: vec![],
+        }
+    }
+
+    /// Adds a counter to the reader, returns its index in [`IntervalStat::stats`].
+    ///
+    /// The first interval of the counter starts from now.
+    pub fn add(&mut self, counter: &Counter) -> Result<usize> {
+        self.add_counter(counter, 1)
+    }
+
+    /// Adds a counter group to the reader, returns its index in [`IntervalStat::stats`].
+    ///
+    /// Siblings are only reported if the leader has [sibling counts][crate::config::StatFormat::siblings]
+    /// enabled. Siblings added to the group afterwards are not supported.
+    pub fn add_group(&mut self, group: &CounterGroup) -> Result<usize> {
+        self.add_counter(group.leader(), 1 + group.siblings().len())
+    }
+
+    fn add_counter(&mut self, counter: &Counter, group_size: usize) -> Result<usize> {
+        // We only change the attr fields related to event config,
+        // there is nothing about `read_format`.
+        let read_format = unsafe { &*counter.attr.get() }.read_format;
+        let mut source = Source {
+            file: counter.file().try_clone()?,
+            read_format,
+            read_buf: vec![0; Stat::read_buf_size(group_size, read_format)],
+            prev: Stat {
+                count: 0,
+                id: None,
+                time_enabled: None,
+                time_running: None,
+                lost_records: None,
+                siblings: vec![],
+            },
+        };
+        source.prev = source.stat()?;
+        self.sources.push(source);
+        Ok(self.sources.len() - 1)
+    }
+
+    /// Returns the interval.
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Waits until the next interval and reads the counters.
+    ///
+    /// Intervals are scheduled from the creation of the reader rather than the
+    /// previous read, so they do not drift. If reading falls behind, the missed
+    /// intervals are skipped and the next stat covers the whole elapsed time.
+    ///
+    /// This is the same as [`Iterator::next`], which never returns `None`.
+    pub fn wait(&mut self) -> Result<IntervalStat> {
+        let elapsed = self.start.elapsed();
+        let interval = self.interval.max(Duration::from_nanos(1));
+        let ticks = (elapsed.as_nanos() / interval.as_nanos()) as u32;
+        self.ticks = (self.ticks + 1).max(ticks);
+        if let Some(wait) =
+            (self.start + interval * self.ticks).checked_duration_since(Instant::now())
+        {
+            thread::sleep(wait);
+        }
+        self.read()
+    }
+
+    /// Reads the counters immediately, returns the deltas since the previous read.
+    pub fn read(&mut self) -> Result<IntervalStat> {
+        let stats = self
+            .sources
+            .iter_mut()
+            .map(|it| {
+                let stat = it.stat()?;
+                let delta = DeltaStat::from_stats(&it.prev, &stat);
+                it.prev = stat;
+                Ok(delta)
+            })
+            .collect::<Result<_>>()?;
+
+        let now = Instant::now();
+        let duration = now - self.last;
+        self.last = now;
+
+        Ok(IntervalStat {
+            elapsed: now - self.start,
+            duration,
+            stats,
+        })
+    }
+
+    /// Reads the counters on a timer thread and calls `f` with every
+    /// interval stat, until `f` returns `false`.
+    pub fn spawn<F>(mut self, mut f: F) -> JoinHandle<()>
+    where
+        F: FnMut(Result<IntervalStat>) -> bool + Send + 'static,
+    {
+        thread::spawn(move || while f(self.wait()) {})
+    }
+
+    /// Creates an asynchronous stream of interval stats, read on a timer thread.
+    ///
+    /// The timer thread exits at the next interval after the stream is dropped.
+    pub fn into_async(self) -> AsyncIntervalReader {
+        let (tx, rx) = mpsc::unbounded();
+        self.spawn(move |stat| tx.unbounded_send(stat).is_ok());
+        AsyncIntervalReader(rx)
+    }
+}
+
+impl Iterator for IntervalReader {
+    type Item = Result<IntervalStat>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.wait())
+    }
+}
+
+impl Source {
+    fn stat(&mut self) -> Result<Stat> {
+        syscall!(read, &self.file, &mut self.read_buf)?;
+        let stat = unsafe { Stat::from_ptr(self.read_buf.as_ptr(), self.read_format) };
+        Ok(stat)
+    }
+}
+
+/// Asynchronous interval reader.
+pub struct AsyncIntervalReader(UnboundedReceiver<Result<IntervalStat>>);
+
+impl AsyncIntervalReader {
+    /// Waits for the next interval stat.
+    pub async fn next(&mut self) -> Option<Result<IntervalStat>> {
+        self.0.next().await
+    }
+}
+
+impl Stream for AsyncIntervalReader {
+    type Item = Result<IntervalStat>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut().0.poll_next_unpin(cx)
+    }
+}
+
+/// Counter statistics of one interval.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IntervalStat {
+    /// Time since the reader was created.
+    pub elapsed: Duration,
+    /// Time since the previous read.
+    pub duration: Duration,
+    /// Stats of the counters in the order they were added.
+    pub stats: Vec<DeltaStat>,
+}
+
+/// Counter statistics delta between two reads.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeltaStat {
+    /// Event count delta.
+    pub count: u64,
+
+    /// Event count delta scaled by `time_enabled / time_running`.
+    ///
+    /// This is the estimated count if the counter had been running for all the
+    /// enabled time. Counters that were disabled for part of the interval are not
+    /// extrapolated to the disabled time, and counters that never ran have zero.
+    pub scaled: f64,
+
+    /// Enabled time delta.
+    pub time_enabled: Option<u64>,
+
+    /// Running time delta.
+    pub time_running: Option<u64>,
+
+    /// Sibling event deltas.
+    pub siblings: Vec<DeltaSiblingStat>,
+}
+
+/// Sibling counter statistics delta between two reads.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeltaSiblingStat {
+    /// Event count delta.
+    pub count: u64,
+
+    /// Event count delta scaled by `time_enabled / time_running` of the leader.
+    pub scaled: f64,
+}
+
+impl DeltaStat {
+    pub(crate) fn from_stats(prev: &Stat, cur: &Stat) -> Self {
+        // The count may go backwards if it was cleared.
+        let delta = |prev: u64, cur: u64| cur.checked_sub(prev).unwrap_or(cur);
+
+        let time_enabled = cur
+            .time_enabled
+            .map(|it| delta(prev.time_enabled.unwrap_or(0), it));
+        let time_running = cur
+            .time_running
+            .map(|it| delta(prev.time_running.unwrap_or(0), it));
+        let scale = match (time_enabled, time_running) {
+            (Some(_), Some(0)) => 0.0,
+            (Some(enabled), Some(running)) => enabled as f64 / running as f64,
+            _ => 1.0,
+        };
+
+        let count = delta(prev.count, cur.count);
+        let siblings = cur
+            .siblings
+            .iter()
+            .enumerate()
+            .map(|(i, it)| {
+                let prev = prev.siblings.get(i).map_or(0, |it| it.count);
+                let count = delta(prev, it.count);
+                DeltaSiblingStat {
+                    count,
+                    scaled: count as f64 * scale,
+                }
+            })
+            .collect();
+
+        Self {
+            count,
+            scaled: count as f64 * scale,
+            time_enabled,
+            time_running,
+            siblings,
+        }
+    }
+
+    /// Returns the fraction of the enabled time that the counter was running.
+    ///
+    /// Less than 1 means the counter was multiplexed, returns `None` if the
+    /// times are not available or the counter was disabled for the whole interval.
+    pub fn running_ratio(&self) -> Option<f64> {
+        match (self.time_enabled?, self.time_running?) {
+            (0, _) => None,
+            (enabled, running) => Some(running as f64 / enabled as f64),
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use std::time::{Duration, Instant};
+
+use super::{DeltaStat, IntervalReader};
+use crate::config::{Cpu, Opts, Proc};
+use crate::count::group::CounterGroup;
+use crate::count::{Counter, SiblingStat, Stat};
+use crate::event::sw::Software;
+
+fn stat(count: u64, time_enabled: u64, time_running: u64, siblings: &[u64]) -> Stat {
+    Stat {
+        count,
+        id: None,
+        time_enabled: Some(time_enabled),
+        time_running: Some(time_running),
+        lost_records: None,
+        siblings: siblings
+            .iter()
+            .map(|it| SiblingStat {
+                count: *it,
+                id: None,
+                lost_records: None,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn test_delta() {
+    let prev = stat(100, 1000, 500, &[10]);
+
+    // Multiplexed for half of the interval.
+    let delta = DeltaStat::from_stats(&prev, &stat(300, 2000, 1000, &[60]));
+    assert_eq!(delta.count, 200);
+    assert_eq!(delta.scaled, 400.0);
+    assert_eq!(delta.time_enabled, Some(1000));
+    assert_eq!(delta.time_running, Some(500));
+    assert_eq!(delta.running_ratio(), Some(0.5));
+    assert_eq!(delta.siblings[0].count, 50);
+    assert_eq!(delta.siblings[0].scaled, 100.0);
+
+    // Disabled for the whole interval.
+    let delta = DeltaStat::from_stats(&prev, &prev);
+    assert_eq!(delta.count, 0);
+    assert_eq!(delta.scaled, 0.0);
+    assert_eq!(delta.running_ratio(), None);
+
+    // Enabled but never scheduled.
+    let delta = DeltaStat::from_stats(&prev, &stat(100, 2000, 500, &[10]));
+    assert_eq!(delta.scaled, 0.0);
+    assert_eq!(delta.running_ratio(), Some(0.0));
+
+    // Count cleared.
+    let delta = DeltaStat::from_stats(&prev, &stat(30, 2000, 1500, &[5]));
+    assert_eq!(delta.count, 30);
+    assert_eq!(delta.siblings[0].count, 5);
+
+    // No enabled and running time.
+    let mut cur = stat(150, 0, 0, &[]);
+    cur.time_enabled = None;
+    cur.time_running = None;
+    let delta = DeltaStat::from_stats(&prev, &cur);
+    assert_eq!(delta.scaled, 50.0);
+    assert_eq!(delta.running_ratio(), None);
+}
+
+fn opts() -> Opts {
+    let mut opts = Opts::default();
+    opts.stat_format.time_enabled = true;
+    opts.stat_format.time_running = true;
+    opts
+}
+
+#[test]
+fn test_wait() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let counter = Counter::new(Software::TaskClock, target, opts()).unwrap();
+
+    let interval = Duration::from_millis(20);
+    let mut reader = IntervalReader::new(interval);
+    assert_eq!(reader.add(&counter).unwrap(), 0);
+
+    // Disabled.
+    let stat = reader.wait().unwrap();
+    assert!(stat.elapsed >= interval);
+    assert_eq!(stat.stats[0].count, 0);
+    assert_eq!(stat.stats[0].running_ratio(), None);
+
+    counter.enable().unwrap();
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(5) {
+        std::hint::black_box(0);
+    }
+    let stat = reader.wait().unwrap();
+    assert!(stat.elapsed >= interval * 2);
+    assert!(stat.stats[0].count > 0);
+    assert_eq!(stat.stats[0].running_ratio(), Some(1.0));
+
+    // Intervals do not drift.
+    for i in 3..6 {
+        let stat = reader.wait().unwrap();
+        assert!(stat.elapsed >= interval * i);
+    }
+}
+
+#[test]
+fn test_group() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let mut opts = opts();
+    opts.stat_format.siblings = true;
+    let leader = Counter::new(Software::TaskClock, target, opts).unwrap();
+    let mut group = CounterGroup::from(leader);
+    group
+        .add(Software::TaskClock, crate::config::sibling::Opts::default())
+        .unwrap();
+
+    let mut reader = IntervalReader::new(Duration::from_millis(10));
+    reader.add_group(&group).unwrap();
+
+    group.enable().unwrap();
+    let stat = reader.wait().unwrap();
+    assert_eq!(stat.stats[0].siblings.len(), 1);
+
+    let handle = reader.spawn({
+        let mut n = 0;
+        move |stat| {
+            assert_eq!(stat.unwrap().stats[0].siblings.len(), 1);
+            n += 1;
+            n < 3
+        }
+    });
+    handle.join().unwrap();
+}
+
+#[test]
+fn test_async() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let counter = Counter::new(Software::TaskClock, target, opts()).unwrap();
+    counter.enable().unwrap();
+
+    let mut reader = IntervalReader::new(Duration::from_millis(10));
+    reader.add(&counter).unwrap();
+    let mut reader = reader.into_async();
+
+    tokio_test::block_on(async {
+        for _ in 0..3 {
+            let stat = reader.next().await.unwrap().unwrap();
+            assert_eq!(stat.stats.len(), 1);
+        }
+    });
+}
@@ -0,0 +1,58 @@
+//! Ioctls on the perf file shared by the counter types.
+//!
+//! With `group` set, the ioctl applies to all counters in the group of the leader file.
+
+use std::fs::File;
+use std::io::Result;
+use std::ptr;
+
+use crate::ffi::{bindings as b, syscall};
+
+fn flags(group: bool) -> u64 {
+    match group {
+        true => b::PERF_IOC_FLAG_GROUP as _,
+        false => 0,
+    }
+}
+
+pub(super) fn id(perf: &File) -> Result<u64> {
+    let mut id = 0;
+
+    let id_addr = ptr::from_mut(&mut id) as u64;
+    syscall!(unsafe, ioctl_arg, perf, b::PERF_IOC_OP_ID as u64, id_addr)?;
+
+    Ok(id)
+}
+
+pub(super) fn enable(perf: &File, group: bool) -> Result<()> {
+    syscall!(
+        unsafe,
+        ioctl_arg,
+        perf,
+        b::PERF_IOC_OP_ENABLE as u64,
+        flags(group)
+    )?;
+    Ok(())
+}
+
+pub(super) fn disable(perf: &File, group: bool) -> Result<()> {
+    syscall!(
+        unsafe,
+        ioctl_arg,
+        perf,
+        b::PERF_IOC_OP_DISABLE as u64,
+        flags(group)
+    )?;
+    Ok(())
+}
+
+pub(super) fn reset(perf: &File, group: bool) -> Result<()> {
+    syscall!(
+        unsafe,
+        ioctl_arg,
+        perf,
+        b::PERF_IOC_OP_RESET as u64,
+        flags(group)
+    )?;
+    Ok(())
+}
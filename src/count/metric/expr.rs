@@ -0,0 +1,324 @@
+use std::io::{Error, ErrorKind, Result};
+
+/// Parsed metric expression.
+///
+/// The syntax is the same as `MetricExpr` in the perf JSON files:
+/// <https://github.com/torvalds/linux/blob/v6.13/tools/perf/util/expr.y>
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Expr {
+    Num(f64),
+    Var(String),
+    Neg(Box<Expr>),
+    Not(Box<Expr>),
+    Bin(Op, Box<Expr>, Box<Expr>),
+    If {
+        then: Box<Expr>,
+        cond: Box<Expr>,
+        other: Box<Expr>,
+    },
+    Min(Box<Expr>, Box<Expr>),
+    Max(Box<Expr>, Box<Expr>),
+    DRatio(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Lt,
+    Gt,
+    And,
+    Or,
+    Xor,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Punct(u8),
+    If,
+    Else,
+}
+
+impl Expr {
+    pub fn parse(src: &str) -> Result<Self> {
+        let tokens = lex(src)?;
+        let mut parser = Parser {
+            src,
+            tokens,
+            pos: 0,
+        };
+        let expr = parser.expr(0)?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some(_) => Err(parser.error("Trailing tokens")),
+        }
+    }
+
+    /// Calls `f` with every variable name in the expression.
+    pub fn visit_vars<'a>(&'a self, f: &mut impl FnMut(&'a str)) {
+        match self {
+            Self::Num(_) => {}
+            Self::Var(name) => f(name),
+            Self::Neg(it) | Self::Not(it) => it.visit_vars(f),
+            Self::Bin(_, a, b) | Self::Min(a, b) | Self::Max(a, b) | Self::DRatio(a, b) => {
+                a.visit_vars(f);
+                b.visit_vars(f);
+            }
+            Self::If { then, cond, other } => {
+                then.visit_vars(f);
+                cond.visit_vars(f);
+                other.visit_vars(f);
+            }
+        }
+    }
+
+    // Division by zero results in NaN, same as perf.
+    pub fn eval(&self, var: &impl Fn(&str) -> Option<f64>) -> Result<f64> {
+        let bool = |it: bool| if it { 1.0 } else { 0.0 };
+        let val = match self {
+            Self::Num(it) => *it,
+            Self::Var(name) => var(name).ok_or_else(|| {
+                let error = format!("Unknown event `{}` in metric expression", name);
+                Error::new(ErrorKind::NotFound, error)
+            })?,
+            Self::Neg(it) => -it.eval(var)?,
+            Self::Not(it) => bool(it.eval(var)? == 0.0),
+            Self::Bin(op, a, b) => {
+                let (a, b) = (a.eval(var)?, b.eval(var)?);
+                match op {
+                    Op::Add => a + b,
+                    Op::Sub => a - b,
+                    Op::Mul => a * b,
+                    Op::Div if b == 0.0 => f64::NAN,
+                    Op::Div => a / b,
+                    Op::Mod if b as i64 == 0 => f64::NAN,
+                    Op::Mod => (a as i64 % b as i64) as f64,
+                    Op::Lt => bool(a < b),
+                    Op::Gt => bool(a > b),
+                    Op::And => bool(a != 0.0 && b != 0.0),
+                    Op::Or => bool(a != 0.0 || b != 0.0),
+                    Op::Xor => bool((a != 0.0) != (b != 0.0)),
+                }
+            }
+            Self::If { then, cond, other } => match cond.eval(var)? != 0.0 {
+                true => then.eval(var)?,
+                false => other.eval(var)?,
+            },
+            Self::Min(a, b) => a.eval(var)?.min(b.eval(var)?),
+            Self::Max(a, b) => a.eval(var)?.max(b.eval(var)?),
+            Self::DRatio(a, b) => {
+                let b = b.eval(var)?;
+                if b == 0.0 {
+                    0.0
+                } else {
+                    a.eval(var)? / b
+                }
+            }
+        };
+        Ok(val)
+    }
+}
+
+fn is_ident_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'.' | b':' | b'@' | b'?' | b'#')
+}
+
+fn lex(src: &str) -> Result<Vec<Token>> {
+    let bytes = src.as_bytes();
+    let mut tokens = vec![];
+    let mut pos = 0;
+    while let Some(&byte) = bytes.get(pos) {
+        match byte {
+            b' ' | b'\t' | b'\n' | b'\r' => pos += 1,
+            byte if byte.is_ascii_digit()
+                || byte == b'.' && bytes.get(pos + 1).is_some_and(u8::is_ascii_digit) =>
+            {
+                let start = pos;
+                while bytes
+                    .get(pos)
+                    .is_some_and(|it| it.is_ascii_digit() || *it == b'.')
+                {
+                    pos += 1;
+                }
+                if let Some(b'e' | b'E') = bytes.get(pos) {
+                    pos += 1;
+                    if let Some(b'-' | b'+') = bytes.get(pos) {
+                        pos += 1;
+                    }
+                    while bytes.get(pos).is_some_and(u8::is_ascii_digit) {
+                        pos += 1;
+                    }
+                }
+                let num = src[start..pos].parse().map_err(|_| {
+                    let error = format!("Invalid number `{}` in `{}`", &src[start..pos], src);
+                    Error::new(ErrorKind::InvalidInput, error)
+                })?;
+                tokens.push(Token::Num(num));
+            }
+            byte if is_ident_byte(byte) || byte == b'\\' => {
+                let mut ident = vec![];
+                while let Some(&byte) = bytes.get(pos) {
+                    match byte {
+                        b'\\' => {
+                            let Some(&next) = bytes.get(pos + 1) else {
+                                break;
+                            };
+                            ident.push(next);
+                            pos += 2;
+                        }
+                        // `-` followed by a letter is part of the name (e.g., `LLC-misses`),
+                        // subtraction of events should be written with spaces (e.g., `a - b`).
+                        b'-' if bytes.get(pos + 1).is_some_and(u8::is_ascii_alphabetic) => {
+                            ident.push(byte);
+                            pos += 1;
+                        }
+                        byte if is_ident_byte(byte) => {
+                            ident.push(byte);
+                            pos += 1;
+                        }
+                        _ => break,
+                    }
+                }
+                let ident = String::from_utf8_lossy(&ident).into_owned();
+                let token = match ident.as_str() {
+                    "if" => Token::If,
+                    "else" => Token::Else,
+                    _ => Token::Ident(ident),
+                };
+                tokens.push(token);
+            }
+            b'+' | b'-' | b'*' | b'/' | b'%' | b'<' | b'>' | b'&' | b'|' | b'^' | b'!' | b'('
+            | b')' | b',' => {
+                tokens.push(Token::Punct(byte));
+                pos += 1;
+            }
+            _ => {
+                let error = format!("Unexpected `{}` in `{}`", byte as char, src);
+                return Err(Error::new(ErrorKind::InvalidInput, error));
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+// Operator precedence, same as perf.
+const LEVELS: [&[(u8, Op)]; 6] = [
+    &[(b'|', Op::Or)],
+    &[(b'^', Op::Xor)],
+    &[(b'&', Op::And)],
+    &[(b'<', Op::Lt), (b'>', Op::Gt)],
+    &[(b'+', Op::Add), (b'-', Op::Sub)],
+    &[(b'*', Op::Mul), (b'/', Op::Div), (b'%', Op::Mod)],
+];
+
+impl Parser<'_> {
+    fn error(&self, msg: &str) -> Error {
+        let error = format!("Invalid metric expression `{}`: {}", self.src, msg);
+        Error::new(ErrorKind::InvalidInput, error)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        let found = self.tokens.get(self.pos) == Some(token);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<()> {
+        match self.eat(&Token::Punct(byte)) {
+            true => Ok(()),
+            false => Err(self.error(&format!("Expected `{}`", byte as char))),
+        }
+    }
+
+    // `a if cond else b` has the lowest precedence and is left associative.
+    fn expr(&mut self, level: usize) -> Result<Expr> {
+        if level == 0 {
+            let mut expr = self.expr(1)?;
+            while self.eat(&Token::If) {
+                let cond = self.expr(1)?;
+                if !self.eat(&Token::Else) {
+                    return Err(self.error("Expected `else`"));
+                }
+                let other = self.expr(1)?;
+                expr = Expr::If {
+                    then: Box::new(expr),
+                    cond: Box::new(cond),
+                    other: Box::new(other),
+                };
+            }
+            return Ok(expr);
+        }
+
+        let Some(ops) = LEVELS.get(level - 1) else {
+            return self.unary();
+        };
+        let mut expr = self.expr(level + 1)?;
+        'outer: loop {
+            for (byte, op) in ops.iter() {
+                if self.eat(&Token::Punct(*byte)) {
+                    let rhs = self.expr(level + 1)?;
+                    expr = Expr::Bin(*op, Box::new(expr), Box::new(rhs));
+                    continue 'outer;
+                }
+            }
+            return Ok(expr);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Num(it)) => Ok(Expr::Num(it)),
+            Some(Token::Punct(b'-')) => Ok(Expr::Neg(Box::new(self.unary()?))),
+            Some(Token::Punct(b'!')) => Ok(Expr::Not(Box::new(self.unary()?))),
+            Some(Token::Punct(b'(')) => {
+                let expr = self.expr(0)?;
+                self.expect(b')')?;
+                Ok(expr)
+            }
+            Some(Token::Ident(name)) => {
+                let func: fn(Box<Expr>, Box<Expr>) -> Expr = match name.as_str() {
+                    "min" => Expr::Min,
+                    "max" => Expr::Max,
+                    "d_ratio" => Expr::DRatio,
+                    // Other functions of perf (e.g., `has_event` or `source_count`).
+                    _ if self.tokens.get(self.pos) == Some(&Token::Punct(b'(')) => {
+                        let error = format!(
+                            "Unsupported function `{}` in metric expression `{}`",
+                            name, self.src
+                        );
+                        return Err(Error::new(ErrorKind::Unsupported, error));
+                    }
+                    _ => return Ok(Expr::Var(name)),
+                };
+                self.expect(b'(')?;
+                let a = self.expr(0)?;
+                self.expect(b',')?;
+                let b = self.expr(0)?;
+                self.expect(b')')?;
+                Ok(func(Box::new(a), Box::new(b)))
+            }
+            _ => Err(self.error("Expected operand")),
+        }
+    }
+}
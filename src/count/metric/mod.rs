@@ -0,0 +1,293 @@
+//! Derived metrics of counter groups.
+//!
+//! A [`NamedGroup`] attaches names to the members of a [`CounterGroup`], and
+//! a [`Metric`] is an expression of these names (e.g., `instructions / cycles`).
+
+mod expr;
+#[cfg(test)]
+mod test;
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+#[cfg(feature = "vendor")]
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+#[cfg(feature = "vendor")]
+use std::path::Path;
+use std::rc::Rc;
+
+use expr::Expr;
+
+use super::group::CounterGroup;
+use super::{Counter, Stat};
+use crate::config::{sibling, Opts, Target};
+#[cfg(feature = "vendor")]
+use crate::event::vendor::{parse_json, read_json_dir, JsonObj};
+use crate::event::Event;
+
+/// Counter group with named members.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use perf_event_open::config::{Cpu, Opts, Proc};
+/// use perf_event_open::count::metric::{Metric, NamedGroup};
+/// use perf_event_open::event::hw::Hardware;
+///
+/// let target = (Proc::ALL, Cpu(0)); // All processes on CPU 0.
+///
+/// let mut group = NamedGroup::new("instructions", Hardware::Instr, target, Opts::default()).unwrap();
+/// group.add("cycles", Hardware::CpuCycle, &Default::default()).unwrap();
+///
+/// let ipc = Metric::new("IPC", "instructions / cycles").unwrap();
+///
+/// group.group().enable().unwrap();
+/// thread::sleep(Duration::from_millis(100));
+/// group.group().disable().unwrap();
+///
+/// let values = group.values().unwrap();
+/// println!("IPC: {}", ipc.eval(&values).unwrap());
+/// ```
+pub struct NamedGroup {
+    group: CounterGroup,
+    names: Vec<String>,
+}
+
+impl NamedGroup {
+    /// Creates group with the leader counter.
+    ///
+    /// [Sibling counts][crate::config::StatFormat::siblings], the
+    /// [enabled time][crate::config::StatFormat::time_enabled] and the
+    /// [running time][crate::config::StatFormat::time_running] are always enabled.
+    pub fn new(
+        name: impl Into<String>,
+        event: impl TryInto<Event, Error = Error>,
+        target: impl Into<Target>,
+        opts: impl Borrow<Opts>,
+    ) -> Result<Self> {
+        let mut opts = opts.borrow().clone();
+        opts.stat_format.siblings = true;
+        opts.stat_format.time_enabled = true;
+        opts.stat_format.time_running = true;
+
+        let leader = Counter::new(event, target, opts)?;
+        Ok(Self {
+            group: CounterGroup::from(leader),
+            names: vec![name.into()],
+        })
+    }
+
+    /// Adds sibling event to group.
+    ///
+    /// Names must be unique in the group.
+    pub fn add(
+        &mut self,
+        name: impl Into<String>,
+        event: impl TryInto<Event, Error = Error>,
+        opts: impl Borrow<sibling::Opts>,
+    ) -> Result<Rc<Counter>> {
+        let name = name.into();
+        if self.names.contains(&name) {
+            let error = format!("Duplicate name `{}` in group", name);
+            return Err(Error::new(ErrorKind::InvalidInput, error));
+        }
+        let sibling = self.group.add(event, opts)?;
+        self.names.push(name);
+        Ok(sibling)
+    }
+
+    /// Returns the counter group.
+    pub fn group(&self) -> &CounterGroup {
+        &self.group
+    }
+
+    /// Returns the names of the leader and siblings in the order they were added.
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Reads the group and returns the scaled values by name.
+    pub fn values(&self) -> Result<Values> {
+        let stat = self.group.leader().stat()?;
+        Ok(Values::from_stat(&self.names, &stat))
+    }
+}
+
+/// Event values by name.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Values {
+    /// Values by name.
+    pub values: BTreeMap<String, f64>,
+}
+
+impl Values {
+    /// Scales the counts of the leader and siblings in `stat` by
+    /// `time_enabled / time_running` to estimate the totals if multiplexing is happening.
+    ///
+    /// `names` are the names of the leader and siblings in order, counts of events
+    /// that never ran are zero.
+    pub fn from_stat(names: &[String], stat: &Stat) -> Self {
+        let scale = match (stat.time_enabled, stat.time_running) {
+            (Some(_), Some(0)) => 0.0,
+            (Some(enabled), Some(running)) => enabled as f64 / running as f64,
+            _ => 1.0,
+        };
+        let counts = Some(stat.count)
+            .into_iter()
+            .chain(stat.siblings.iter().map(|it| it.count));
+        let values = names
+            .iter()
+            .zip(counts)
+            .map(|(name, count)| (name.clone(), count as f64 * scale))
+            .collect();
+        Self { values }
+    }
+
+    /// Returns the value by name, case-insensitively if there is no exact match.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.values.get(name).copied().or_else(|| {
+            self.values
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| *v)
+        })
+    }
+
+    /// Inserts the value, this can be used to provide the values of other groups,
+    /// constants like `#smt_on`, or the results of metrics used by other metrics.
+    pub fn insert(&mut self, name: impl Into<String>, value: f64) {
+        self.values.insert(name.into(), value);
+    }
+}
+
+/// Metric derived from event values.
+///
+/// The expression syntax is the same as `MetricExpr` in the perf JSON files,
+/// which supports the `+`, `-`, `*`, `/`, `%`, `<`, `>`, `&`, `|`, `^` and `!`
+/// operators, `a if cond else b`, and the `min`, `max` and `d_ratio` functions.
+///
+/// Event names may contain `.`, `:`, `@` and `-` followed by a letter (e.g., `LLC-misses`),
+/// subtraction of events should be written with spaces (e.g., `a - b`). Other characters
+/// can be escaped with `\`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Metric {
+    /// Metric name (e.g., `IPC`).
+    pub name: String,
+    /// Brief description.
+    pub desc: Option<String>,
+    /// Metric groups this metric belongs to (e.g., `Summary` or `TopdownL1`).
+    pub groups: Vec<String>,
+    /// The scale to multiply the result by for display (e.g., `100` for percentages).
+    pub scale: f64,
+    /// The unit of the scaled result (e.g., `%`).
+    pub unit: Option<String>,
+    expr: Expr,
+}
+
+impl Metric {
+    /// Parses the metric expression.
+    pub fn new(name: impl Into<String>, expr: &str) -> Result<Self> {
+        Ok(Self {
+            name: name.into(),
+            desc: None,
+            groups: vec![],
+            scale: 1.0,
+            unit: None,
+            expr: Expr::parse(expr)?,
+        })
+    }
+
+    /// Loads metrics from the perf JSON file content.
+    ///
+    /// Entries without `MetricName` and `MetricExpr` (e.g., events) are skipped.
+    ///
+    /// Requires the `vendor` feature.
+    #[cfg(feature = "vendor")]
+    pub fn from_json(json: &str) -> Result<Vec<Self>> {
+        parse_json(json)?
+            .iter()
+            .filter_map(Self::from_json_obj)
+            .collect()
+    }
+
+    /// Loads metrics from all JSON files in a CPU model directory
+    /// (e.g., `tools/perf/pmu-events/arch/x86/skylake`).
+    ///
+    /// Metrics using functions not supported by the expression parser
+    /// (e.g., `has_event` or `source_count`) are skipped.
+    ///
+    /// Requires the `vendor` feature.
+    #[cfg(feature = "vendor")]
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Vec<Self>> {
+        let mut metrics = vec![];
+        for (_, objs) in read_json_dir(dir.as_ref())? {
+            for obj in objs {
+                match Self::from_json_obj(&obj) {
+                    Some(Ok(metric)) => metrics.push(metric),
+                    Some(Err(e)) if e.kind() == ErrorKind::Unsupported => {}
+                    Some(Err(e)) => return Err(e),
+                    None => {}
+                }
+            }
+        }
+        Ok(metrics)
+    }
+
+    /// Loads metrics from a perf JSON file.
+    ///
+    /// Requires the `vendor` feature.
+    #[cfg(feature = "vendor")]
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Vec<Self>> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    #[cfg(feature = "vendor")]
+    fn from_json_obj(obj: &JsonObj) -> Option<Result<Self>> {
+        let field = |key: &str| obj.get(key).map(|it| it.trim());
+        let (name, expr) = (field("MetricName")?, field("MetricExpr")?);
+
+        let metric = Self::new(name, expr).and_then(|mut metric| {
+            metric.desc = field("BriefDescription").map(str::to_string);
+            metric.groups = field("MetricGroup")
+                .map(|it| it.split(';').map(str::to_string).collect())
+                .unwrap_or_default();
+            // `ScaleUnit` is the scale followed by the unit (e.g., `100%` or `1GHz`).
+            if let Some(scale_unit) = field("ScaleUnit") {
+                let split = scale_unit
+                    .find(|it: char| !it.is_ascii_digit() && it != '.' && it != 'e')
+                    .unwrap_or(scale_unit.len());
+                let (scale, unit) = scale_unit.split_at(split);
+                metric.scale = scale.parse().map_err(|_| {
+                    let error = format!("Invalid `ScaleUnit` of metric `{}`", name);
+                    Error::new(ErrorKind::InvalidData, error)
+                })?;
+                metric.unit = Some(unit.to_string()).filter(|it| !it.is_empty());
+            }
+            Ok(metric)
+        });
+        Some(metric)
+    }
+
+    /// Returns the names of the events used by the metric, without duplicates.
+    pub fn events(&self) -> Vec<&str> {
+        let mut events = vec![];
+        self.expr.visit_vars(&mut |it| {
+            if !events.contains(&it) {
+                events.push(it);
+            }
+        });
+        events
+    }
+
+    /// Evaluates the metric with the event values, without [scaling][Self::scale].
+    ///
+    /// Division by zero results in NaN, same as perf, use `d_ratio` to get zero instead.
+    pub fn eval(&self, values: &Values) -> Result<f64> {
+        self.expr.eval(&|name| values.get(name))
+    }
+}
@@ -0,0 +1,203 @@
+#[cfg(feature = "vendor")]
+use std::fs;
+use std::io::ErrorKind;
+
+use super::{Metric, NamedGroup, Values};
+use crate::config::sibling::Opts as SiblingOpts;
+use crate::config::{Cpu, Opts, Proc};
+use crate::count::{SiblingStat, Stat};
+use crate::event::sw::Software;
+use crate::test::busy_loop;
+
+fn values(pairs: &[(&str, f64)]) -> Values {
+    let mut values = Values::default();
+    for (name, value) in pairs {
+        values.insert(*name, *value);
+    }
+    values
+}
+
+fn eval(expr: &str, values: &Values) -> f64 {
+    Metric::new("test", expr).unwrap().eval(values).unwrap()
+}
+
+#[test]
+fn test_eval() {
+    let values = values(&[
+        ("instructions", 300.0),
+        ("cycles", 100.0),
+        ("LLC-misses", 5.0),
+        ("LLC-loads", 20.0),
+        ("INST_RETIRED.ANY", 1000.0),
+        ("cpu@event=0x3c@", 500.0),
+        ("#smt_on", 1.0),
+    ]);
+
+    assert_eq!(eval("instructions / cycles", &values), 3.0);
+    assert_eq!(eval("LLC-misses / LLC-loads", &values), 0.25);
+    assert_eq!(eval("LLC-loads - LLC-misses", &values), 15.0);
+    assert_eq!(eval("inst_retired.any / 4", &values), 250.0);
+    assert_eq!(eval("cpu@event\\=0x3c@ * 2", &values), 1000.0);
+
+    // Precedence and associativity.
+    assert_eq!(eval("1 + 2 * 3 - 4 / 2", &values), 5.0);
+    assert_eq!(eval("(1 + 2) * 3", &values), 9.0);
+    assert_eq!(eval("10 - 2 - 3", &values), 5.0);
+    assert_eq!(eval("-cycles + 1e2", &values), 0.0);
+    assert_eq!(eval("7 % 4", &values), 3.0);
+    assert_eq!(eval("1 < 2 & 3 > 4 | !0", &values), 1.0);
+    assert_eq!(eval("1 ^ 1", &values), 0.0);
+
+    // Functions and conditionals.
+    assert_eq!(eval("min(instructions, cycles)", &values), 100.0);
+    assert_eq!(eval("max(instructions, cycles)", &values), 300.0);
+    assert_eq!(eval("d_ratio(cycles, 0)", &values), 0.0);
+    assert!(eval("cycles / 0", &values).is_nan());
+    assert_eq!(eval("cycles / 2 if #smt_on else cycles", &values), 50.0);
+    assert_eq!(eval("1 if 0 else 2 if 0 else 3", &values), 3.0);
+
+    let metric = Metric::new("test", "unknown + 1").unwrap();
+    assert!(metric.eval(&values).is_err());
+}
+
+#[test]
+fn test_parse_error() {
+    for expr in ["", "1 +", "(1", "1 if 1", "min(1)", "1 2", "a $ b"] {
+        assert!(Metric::new("test", expr).is_err(), "{}", expr);
+    }
+    let error = Metric::new("test", "a if has_event(a) else 0").unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::Unsupported);
+}
+
+#[test]
+fn test_events() {
+    let metric = Metric::new("test", "(a + b) / a if c > 0 else d_ratio(b, e)").unwrap();
+    assert_eq!(metric.events(), ["a", "b", "c", "e"]);
+}
+
+#[cfg(feature = "vendor")]
+#[test]
+fn test_from_json() {
+    let json = r#"[
+        {
+            "BriefDescription": "Instructions Per Cycle (per Logical Processor)",
+            "MetricExpr": "INST_RETIRED.ANY / CPU_CLK_UNHALTED.THREAD",
+            "MetricGroup": "Ret;Summary",
+            "MetricName": "IPC"
+        },
+        {
+            "MetricExpr": "topdown\\-retiring / (topdown\\-fe\\-bound + topdown\\-bad\\-spec + topdown\\-retiring + topdown\\-be\\-bound)",
+            "MetricName": "tma_retiring",
+            "ScaleUnit": "100%"
+        },
+        {
+            "EventCode": "0x3c",
+            "EventName": "CPU_CLK_UNHALTED.THREAD"
+        }
+    ]"#;
+    let metrics = Metric::from_json(json).unwrap();
+    assert_eq!(metrics.len(), 2);
+
+    let ipc = &metrics[0];
+    assert_eq!(ipc.name, "IPC");
+    assert_eq!(ipc.groups, ["Ret", "Summary"]);
+    assert_eq!(ipc.scale, 1.0);
+    assert_eq!(ipc.unit, None);
+    assert_eq!(
+        ipc.events(),
+        ["INST_RETIRED.ANY", "CPU_CLK_UNHALTED.THREAD"]
+    );
+
+    let retiring = &metrics[1];
+    assert_eq!(retiring.scale, 100.0);
+    assert_eq!(retiring.unit.as_deref(), Some("%"));
+    let values = values(&[
+        ("topdown-retiring", 4.0),
+        ("topdown-fe-bound", 2.0),
+        ("topdown-bad-spec", 1.0),
+        ("topdown-be-bound", 3.0),
+    ]);
+    assert_eq!(retiring.eval(&values).unwrap(), 0.4);
+
+    let json = r#"[{"MetricName": "Bad", "MetricExpr": "a +"}]"#;
+    assert!(Metric::from_json(json).is_err());
+}
+
+#[cfg(feature = "vendor")]
+#[test]
+fn test_from_dir() {
+    let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+    fs::create_dir_all(&dir).unwrap();
+    let json = r#"[
+        {"MetricName": "IPC", "MetricExpr": "INST_RETIRED.ANY / CPU_CLK_UNHALTED.THREAD"},
+        {"MetricName": "Slots", "MetricExpr": "TOPDOWN.SLOTS if has_event(TOPDOWN.SLOTS) else 0"}
+    ]"#;
+    fs::write(dir.join("metrics.json"), json).unwrap();
+    let metrics = Metric::from_dir(&dir);
+
+    let json = r#"[{"MetricName": "Bad", "MetricExpr": "a +"}]"#;
+    fs::write(dir.join("bad.json"), json).unwrap();
+    let bad = Metric::from_dir(&dir);
+    fs::remove_dir_all(&dir).unwrap();
+
+    // Metrics with unsupported functions are skipped.
+    let metrics = metrics.unwrap();
+    assert_eq!(metrics.len(), 1);
+    assert_eq!(metrics[0].name, "IPC");
+    // Invalid metrics are still errors.
+    assert!(bad.is_err());
+}
+
+#[test]
+fn test_from_stat() {
+    let names = ["a".to_string(), "b".to_string()];
+    let mut stat = Stat {
+        count: 100,
+        id: None,
+        time_enabled: Some(200),
+        time_running: Some(100),
+        lost_records: None,
+        siblings: vec![SiblingStat {
+            count: 50,
+            id: None,
+            lost_records: None,
+        }],
+    };
+
+    let values = Values::from_stat(&names, &stat);
+    assert_eq!(values.get("a"), Some(200.0));
+    assert_eq!(values.get("b"), Some(100.0));
+
+    stat.time_running = Some(0);
+    let values = Values::from_stat(&names, &stat);
+    assert_eq!(values.get("a"), Some(0.0));
+
+    stat.time_enabled = None;
+    stat.time_running = None;
+    let values = Values::from_stat(&names, &stat);
+    assert_eq!(values.get("A"), Some(100.0));
+}
+
+#[test]
+fn test_named_group() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let mut group = NamedGroup::new("a", Software::TaskClock, target, Opts::default()).unwrap();
+    group
+        .add("b", Software::TaskClock, SiblingOpts::default())
+        .unwrap();
+    assert!(group
+        .add("a", Software::TaskClock, SiblingOpts::default())
+        .is_err());
+    assert_eq!(group.names(), ["a", "b"]);
+
+    group.group().enable().unwrap();
+    busy_loop();
+    group.group().disable().unwrap();
+
+    let values = group.values().unwrap();
+    assert!(values.get("a").unwrap() > 0.0);
+    assert!(values.get("b").unwrap() > 0.0);
+
+    let metric = Metric::new("test", "a / b").unwrap();
+    assert!(metric.eval(&values).unwrap() > 0.0);
+}
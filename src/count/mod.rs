@@ -1,21 +1,36 @@
 use std::borrow::Borrow;
 use std::cell::UnsafeCell;
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::fs::File;
 use std::io::{self, Error, ErrorKind, Result};
 use std::os::fd::AsRawFd;
-use std::ptr;
 use std::sync::Arc;
 
-use super::sample::Sampler;
+use super::sample::{OverwriteSampler, Sampler};
 use crate::config::attr::from;
 use crate::config::{Opts, Target};
 use crate::event::Event;
 use crate::ffi::{bindings as b, syscall, Attr};
 
+pub mod cgroup;
+pub mod command;
+mod error;
+mod fast;
 pub mod group;
+mod interval;
+mod ioctl;
+pub mod metric;
+pub mod plan;
+pub mod process;
+pub mod set;
+mod shared;
 mod stat;
+pub mod topdown;
 
+pub use error::*;
+pub use fast::*;
+pub use interval::*;
+pub use shared::*;
 pub use stat::*;
 
 /// Event counter.
@@ -79,17 +94,26 @@ pub struct Counter {
     pub(crate) attr: UnsafeCell<Attr>,
     pub(crate) perf: Arc<File>,
     pub(crate) read_buf: UnsafeCell<Vec<u8>>,
+    // Keep strings pointed by the event config alive, see `EventConfig::keep_alive`.
+    // It is only replaced by `switch_to`, and never read.
+    #[allow(dead_code)]
+    pub(crate) keep_alive: UnsafeCell<Option<Arc<CString>>>,
 }
 
 impl Counter {
     /// Creates a new event counter.
+    ///
+    /// Errors returned by `perf_event_open` carry the errno, use [`OpenError::diagnose`]
+    /// to find out what went wrong.
     pub fn new(
         event: impl TryInto<Event, Error = io::Error>,
         target: impl Into<Target>,
         opts: impl Borrow<Opts>,
     ) -> Result<Self> {
         let target = target.into();
-        let attr = from(event.try_into()?.0, opts.borrow())?;
+        let Event(mut event_cfg) = event.try_into()?;
+        let keep_alive = event_cfg.keep_alive.take();
+        let attr = from(event_cfg, opts.borrow())?;
         let flags = target.flags | b::PERF_FLAG_FD_CLOEXEC as u64;
         let perf = syscall!(perf_event_open, &attr, target.pid, target.cpu, -1, flags)?;
         // Now there is only one event in the group, if in the future
@@ -102,6 +126,7 @@ impl Counter {
             attr: UnsafeCell::new(attr),
             perf: Arc::new(perf),
             read_buf: UnsafeCell::new(read_buf),
+            keep_alive: UnsafeCell::new(keep_alive),
         })
     }
 
@@ -152,6 +177,33 @@ impl Counter {
         }
     }
 
+    /// Create a sampler in overwrite mode for this counter.
+    ///
+    /// The counter must be created with [`Opts::write_backward`][crate::config::Opts::write_backward],
+    /// see [`OverwriteSampler`] for details. 1 + 2^`exp` pages will be allocated for the ring buffer.
+    ///
+    /// A counter cannot have multiple samplers simultaneously, see [`Counter::sampler`] for details.
+    pub fn overwrite_sampler(&self, exp: u8) -> Result<OverwriteSampler> {
+        if Arc::strong_count(&self.perf) == 1 {
+            let attr = unsafe { &*self.attr.get() };
+            OverwriteSampler::new(Arc::clone(&self.perf), attr, exp)
+        } else {
+            let error = "There is already a sampler attached to this counter.";
+            Err(Error::new(ErrorKind::AlreadyExists, error))
+        }
+    }
+
+    /// Create a self-monitoring reader for this counter.
+    ///
+    /// The reader maps the metadata page of the counter to read the count
+    /// without system calls if possible, see [`FastReader`] for details.
+    ///
+    /// A counter cannot have a reader and a sampler simultaneously, attempting to
+    /// create a reader while a sampler is active will result in [`ErrorKind::AlreadyExists`].
+    pub fn fast_reader(&self) -> Result<FastReader<'_>> {
+        FastReader::new(self)
+    }
+
     /// Returns the file handle opened by [`perf_event_open`](https://man7.org/linux/man-pages/man2/perf_event_open.2.html)
     /// system call for the current event.
     ///
@@ -167,46 +219,21 @@ impl Counter {
     ///
     /// This is the same as [`Stat::id`], [`SiblingStat::id`] and [`RecordId::id`][crate::sample::record::RecordId::id].
     pub fn id(&self) -> Result<u64> {
-        let mut id = 0;
-
-        let id_addr = ptr::from_mut(&mut id) as u64;
-        syscall!(
-            unsafe,
-            ioctl_arg,
-            &self.perf,
-            b::PERF_IOC_OP_ID as u64,
-            id_addr
-        )?;
-
-        Ok(id)
+        ioctl::id(&self.perf)
     }
 
     /// Enable counter.
     ///
     /// Counter will start to accumulate event counts.
     pub fn enable(&self) -> Result<()> {
-        syscall!(
-            unsafe,
-            ioctl_arg,
-            &self.perf,
-            b::PERF_IOC_OP_ENABLE as u64,
-            0
-        )?;
-        Ok(())
+        ioctl::enable(&self.perf, false)
     }
 
     /// Disable counter.
     ///
     /// Counter will stop to accumulate event counts.
     pub fn disable(&self) -> Result<()> {
-        syscall!(
-            unsafe,
-            ioctl_arg,
-            &self.perf,
-            b::PERF_IOC_OP_DISABLE as u64,
-            0
-        )?;
-        Ok(())
+        ioctl::disable(&self.perf, false)
     }
 
     /// Clear event count.
@@ -214,14 +241,7 @@ impl Counter {
     /// This will only clear the event counts in the statistics,
     /// other fields (such as `time_enabled`) are not affected.
     pub fn clear_count(&self) -> Result<()> {
-        syscall!(
-            unsafe,
-            ioctl_arg,
-            &self.perf,
-            b::PERF_IOC_OP_RESET as u64,
-            0
-        )?;
-        Ok(())
+        ioctl::reset(&self.perf, false)
     }
 
     /// Returns counter statistics.
@@ -372,7 +392,7 @@ impl Counter {
             (attr.config3 = event_cfg.config3);
             attr.bp_type = event_cfg.bp_type;
 
-            let attr_addr = ptr::from_mut(attr) as u64;
+            let attr_addr = std::ptr::from_mut(attr) as u64;
             syscall!(
                 unsafe,
                 ioctl_arg,
@@ -381,6 +401,9 @@ impl Counter {
                 attr_addr
             )?;
 
+            // The new event config is in use now, so it is safe to drop the old one.
+            unsafe { *self.keep_alive.get() = event_cfg.keep_alive };
+
             Ok(())
         }
         #[cfg(not(feature = "linux-4.17"))]
@@ -0,0 +1,280 @@
+#[cfg(test)]
+mod test;
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fs::{self, read_dir};
+use std::io::{Error, ErrorKind, Result};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::metric::{NamedGroup, Values};
+use crate::config::{sibling, Cpu, Opts, Proc, Target};
+use crate::event::Event;
+use crate::ffi::bindings as b;
+
+/// Events split into groups that fit the PMU.
+///
+/// A group is only scheduled if all of its members can be put onto the PMU
+/// at the same time, so a group with more hardware events than the available
+/// counters never runs. This type splits the events in order into several
+/// groups that are multiplexed together.
+///
+/// Counts are scaled by the running time of their groups, so ratios of events in
+/// different groups are estimations. [`PlanValues::same_group`] tells whether a ratio
+/// is still exact.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use perf_event_open::config::{Cpu, Opts, Proc};
+/// use perf_event_open::count::plan::GroupPlan;
+/// use perf_event_open::event::hw::Hardware;
+///
+/// let target = (Proc::ALL, Cpu(0)); // All processes on CPU 0.
+/// let events = [
+///     ("instructions", Hardware::Instr),
+///     ("cycles", Hardware::CpuCycle),
+///     ("cache-references", Hardware::CacheAccess),
+///     ("cache-misses", Hardware::CacheMiss),
+///     ("branches", Hardware::BranchInstr),
+///     ("branch-misses", Hardware::BranchMiss),
+///     ("ref-cycles", Hardware::RefCpuCycle),
+/// ];
+/// let plan = GroupPlan::new(events, target, Opts::default()).unwrap();
+/// println!("{} groups", plan.groups().len());
+///
+/// plan.enable().unwrap();
+/// thread::sleep(Duration::from_millis(100));
+/// plan.disable().unwrap();
+///
+/// let values = plan.values().unwrap();
+/// let ipc = values.values.get("instructions").unwrap() / values.values.get("cycles").unwrap();
+/// let exact = values.same_group("instructions", "cycles");
+/// println!("IPC: {} (exact: {})", ipc, exact);
+/// ```
+pub struct GroupPlan {
+    groups: Vec<NamedGroup>,
+}
+
+impl GroupPlan {
+    /// Splits the events by trial scheduling.
+    ///
+    /// Events are added to the current group one by one, and the group is enabled for
+    /// two multiplexing intervals of the PMU (`perf_event_mux_interval_ms` in sysfs)
+    /// to check whether it is still scheduled. If not, the last event starts
+    /// a new group. This also takes counters used by others (e.g., the NMI watchdog)
+    /// into account.
+    ///
+    /// The trial groups are opened on the target if it is a CPU-wide target,
+    /// otherwise on the current thread with the same options, since a task
+    /// that is not running would never be scheduled.
+    pub fn new<N, E>(
+        events: impl IntoIterator<Item = (N, E)>,
+        target: impl Into<Target>,
+        opts: impl Borrow<Opts>,
+    ) -> Result<Self>
+    where
+        N: Into<String>,
+        E: TryInto<Event, Error = Error>,
+    {
+        Self::plan(events, target.into(), opts.borrow(), None)
+    }
+
+    /// Splits the events into groups of at most `size` members.
+    ///
+    /// This can be used if the number of available counters is known,
+    /// e.g., from the PMU documentation.
+    pub fn with_size<N, E>(
+        events: impl IntoIterator<Item = (N, E)>,
+        target: impl Into<Target>,
+        opts: impl Borrow<Opts>,
+        size: usize,
+    ) -> Result<Self>
+    where
+        N: Into<String>,
+        E: TryInto<Event, Error = Error>,
+    {
+        if size == 0 {
+            let error = "Group size must be greater than 0";
+            return Err(Error::new(ErrorKind::InvalidInput, error));
+        }
+        Self::plan(events, target.into(), opts.borrow(), Some(size))
+    }
+
+    fn plan<N, E>(
+        events: impl IntoIterator<Item = (N, E)>,
+        target: Target,
+        opts: &Opts,
+        size: Option<usize>,
+    ) -> Result<Self>
+    where
+        N: Into<String>,
+        E: TryInto<Event, Error = Error>,
+    {
+        let mut members: Vec<(String, Event)> = vec![];
+        for (name, event) in events {
+            let name = name.into();
+            if members.iter().any(|(it, _)| *it == name) {
+                let error = format!("Duplicate name `{}` in plan", name);
+                return Err(Error::new(ErrorKind::InvalidInput, error));
+            }
+            members.push((name, event.try_into()?));
+        }
+        if members.is_empty() {
+            let error = "No events to plan";
+            return Err(Error::new(ErrorKind::InvalidInput, error));
+        }
+
+        // Members of the current group are `members[start..end]`.
+        let mut splits = vec![];
+        let mut start = 0;
+        for end in 2..=members.len() {
+            let full = match size {
+                Some(size) => end - start > size,
+                None => !fits(&members[start..end], &target, opts)?,
+            };
+            if full {
+                splits.push(start..end - 1);
+                start = end - 1;
+            }
+        }
+        splits.push(start..members.len());
+
+        let groups = splits
+            .into_iter()
+            .map(|it| open(&members[it], target.clone(), opts))
+            .collect::<Result<_>>()?;
+        Ok(Self { groups })
+    }
+
+    /// Returns the groups in order.
+    pub fn groups(&self) -> &[NamedGroup] {
+        &self.groups
+    }
+
+    /// Enables all groups.
+    pub fn enable(&self) -> Result<()> {
+        self.groups.iter().try_for_each(|it| it.group().enable())
+    }
+
+    /// Disables all groups.
+    pub fn disable(&self) -> Result<()> {
+        self.groups.iter().try_for_each(|it| it.group().disable())
+    }
+
+    /// Clears the counts of all groups.
+    pub fn clear_count(&self) -> Result<()> {
+        self.groups
+            .iter()
+            .try_for_each(|it| it.group().clear_count())
+    }
+
+    /// Reads all groups and returns the scaled values by name.
+    pub fn values(&self) -> Result<PlanValues> {
+        let mut values = Values::default();
+        let mut groups = BTreeMap::new();
+        for (i, group) in self.groups.iter().enumerate() {
+            values.values.extend(group.values()?.values);
+            groups.extend(group.names().iter().map(|it| (it.clone(), i)));
+        }
+        Ok(PlanValues { values, groups })
+    }
+}
+
+/// Values of a [`GroupPlan`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlanValues {
+    /// Scaled values by name, see [`Values::from_stat`] for details.
+    pub values: Values,
+
+    /// Group indexes by name.
+    pub groups: BTreeMap<String, usize>,
+}
+
+impl PlanValues {
+    /// Returns `true` if both events are in the same group, so that their
+    /// ratio was measured for the same set of executed instructions.
+    pub fn same_group(&self, a: &str, b: &str) -> bool {
+        match (self.groups.get(a), self.groups.get(b)) {
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+fn open(members: &[(String, Event)], target: Target, opts: &Opts) -> Result<NamedGroup> {
+    let sibling_opts = sibling::Opts {
+        exclude: opts.exclude.clone(),
+        inherit: opts.inherit,
+        on_execve: opts.on_execve,
+        ..Default::default()
+    };
+
+    let (name, event) = &members[0];
+    let mut group = NamedGroup::new(name, event, target, opts)?;
+    for (name, event) in &members[1..] {
+        group.add(name, event, &sibling_opts)?;
+    }
+    Ok(group)
+}
+
+// Enables the group for a couple of multiplexing rotations and checks whether it was scheduled.
+fn fits(members: &[(String, Event)], target: &Target, opts: &Opts) -> Result<bool> {
+    let mut opts = opts.clone();
+    opts.enable = false;
+    let target = match target.pid {
+        -1 => target.clone(),
+        _ => (Proc::CURRENT, Cpu::ALL).into(),
+    };
+    if target.pid != -1 {
+        // Trial groups on the current thread should not follow child tasks.
+        opts.inherit = None;
+        opts.on_execve = None;
+    }
+
+    let group = match open(members, target, &opts) {
+        Ok(it) => it,
+        // Some PMUs validate whether the group fits when a sibling is added:
+        // https://github.com/torvalds/linux/blob/v6.13/arch/x86/events/core.c
+        Err(e) if e.raw_os_error() == Some(libc::EINVAL) => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    // A group that fits may wait for other flexible groups on the PMU to rotate out.
+    let ty = unsafe { &*group.group().leader().attr.get() }.type_;
+    let trial = mux_interval(ty) * 2;
+    group.group().enable()?;
+    let start = Instant::now();
+    while start.elapsed() < trial {
+        std::hint::spin_loop();
+    }
+    group.group().disable()?;
+
+    let stat = group.group().leader().stat()?;
+    Ok(stat.time_running.is_some_and(|it| it > 0))
+}
+
+// Flexible groups are rotated every `perf_event_mux_interval_ms` of the PMU:
+// https://github.com/torvalds/linux/blob/v6.13/kernel/events/core.c
+fn mux_interval(ty: u32) -> Duration {
+    // Generic hardware events are handled by the core PMU, which has the raw type on x86.
+    let ty = match ty {
+        b::PERF_TYPE_HARDWARE | b::PERF_TYPE_HW_CACHE => b::PERF_TYPE_RAW,
+        _ => ty,
+    };
+    let read =
+        |path: PathBuf| -> Option<u64> { fs::read_to_string(path).ok()?.trim().parse().ok() };
+    let ms = read_dir("/sys/bus/event_source/devices")
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| read(path.join("type")) == Some(ty as _))
+        .and_then(|path| read(path.join("perf_event_mux_interval_ms")));
+    // `PERF_CPU_HRTIMER` is 4ms with `HZ=250`.
+    Duration::from_millis(ms.unwrap_or(4).max(1))
+}
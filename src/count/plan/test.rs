@@ -0,0 +1,52 @@
+use super::GroupPlan;
+use crate::config::{Cpu, Opts, Proc};
+use crate::event::sw::Software;
+use crate::test::busy_loop;
+
+fn events() -> Vec<(String, Software)> {
+    (0..5)
+        .map(|i| (format!("clock{}", i), Software::TaskClock))
+        .collect()
+}
+
+#[test]
+fn test_with_size() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let plan = GroupPlan::with_size(events(), target, Opts::default(), 2).unwrap();
+    let sizes: Vec<_> = plan.groups().iter().map(|it| it.names().len()).collect();
+    assert_eq!(sizes, [2, 2, 1]);
+
+    plan.enable().unwrap();
+    busy_loop();
+    plan.disable().unwrap();
+
+    let values = plan.values().unwrap();
+    assert_eq!(values.values.values.len(), 5);
+    assert!(values.values.get("clock4").unwrap() > 0.0);
+    assert!(values.same_group("clock0", "clock1"));
+    assert!(!values.same_group("clock1", "clock2"));
+    assert!(!values.same_group("clock0", "unknown"));
+}
+
+#[test]
+fn test_trial() {
+    // Software events are always scheduled.
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let plan = GroupPlan::new(events(), target, Opts::default()).unwrap();
+    assert_eq!(plan.groups().len(), 1);
+    assert_eq!(plan.groups()[0].names().len(), 5);
+}
+
+#[test]
+fn test_invalid() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let result = GroupPlan::with_size(events(), target, Opts::default(), 0);
+    assert!(result.is_err());
+
+    let mut events = events();
+    events.push(("clock0".to_string(), Software::TaskClock));
+    assert!(GroupPlan::new(events, target, Opts::default()).is_err());
+
+    let events: Vec<(String, Software)> = vec![];
+    assert!(GroupPlan::new(events, target, Opts::default()).is_err());
+}
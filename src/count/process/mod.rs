@@ -0,0 +1,176 @@
+#[cfg(test)]
+mod test;
+
+use std::borrow::Borrow;
+use std::collections::BTreeMap;
+use std::fs::read_dir;
+use std::io::{Error, Result};
+
+use super::set::sum;
+use super::{Counter, Stat};
+use crate::config::{Cpu, Opts, Proc};
+use crate::event::Event;
+
+/// Counters attached to every thread of a process, similar to the `perf stat -p` command.
+///
+/// A counter with `Proc(pid)` only monitors the thread with the tid, and
+/// [`Inherit`][crate::config::Inherit] only covers threads created after the counter
+/// is attached. This type opens a counter for each existing thread in `/proc/<pid>/task`,
+/// and [`refresh`][Self::refresh] picks up new threads and cleans up exited threads.
+///
+/// Counts of exited threads are kept in the process totals. Since new threads are picked
+/// up by refreshing, `inherit` should not be set to avoid counting these threads twice.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use perf_event_open::config::{Opts, Proc};
+/// use perf_event_open::count::process::ProcessCounter;
+/// use perf_event_open::event::sw::Software;
+///
+/// let pid = Proc(std::process::id());
+/// let mut counter = ProcessCounter::new(Software::TaskClock, pid, Opts::default()).unwrap();
+///
+/// counter.enable().unwrap();
+/// for _ in 0..10 {
+///     thread::sleep(Duration::from_millis(10));
+///     counter.refresh().unwrap();
+/// }
+/// counter.disable().unwrap();
+///
+/// let stat = counter.stat().unwrap();
+/// for (tid, stat) in &stat.threads {
+///     println!("Thread {}: {} ns", tid, stat.count);
+/// }
+/// println!("Process: {} ns", stat.sum.count);
+/// ```
+pub struct ProcessCounter {
+    pid: u32,
+    event: Event,
+    opts: Opts,
+    threads: BTreeMap<u32, Counter>,
+    exited: Vec<Stat>,
+}
+
+impl ProcessCounter {
+    /// Opens the event on every thread of the process.
+    pub fn new(
+        event: impl TryInto<Event, Error = Error>,
+        pid: Proc,
+        opts: impl Borrow<Opts>,
+    ) -> Result<Self> {
+        let mut counter = Self {
+            pid: pid.0,
+            event: event.try_into()?,
+            opts: opts.borrow().clone(),
+            threads: BTreeMap::new(),
+            exited: vec![],
+        };
+        counter.refresh()?;
+        Ok(counter)
+    }
+
+    /// Returns the process ID.
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// Returns the counters by thread ID.
+    pub fn threads(&self) -> &BTreeMap<u32, Counter> {
+        &self.threads
+    }
+
+    /// Opens counters for new threads, and removes counters of exited threads.
+    ///
+    /// Counters of new threads are enabled if the counters were [enabled][Self::enable].
+    pub fn refresh(&mut self) -> Result<()> {
+        let tids = tids(self.pid)?;
+
+        let exited: Vec<_> = self
+            .threads
+            .keys()
+            .filter(|it| !tids.contains(it))
+            .copied()
+            .collect();
+        for tid in exited {
+            let counter = self.threads.remove(&tid).unwrap();
+            // The counter of an exited thread keeps its final count.
+            if let Ok(stat) = counter.stat() {
+                self.exited.push(stat);
+            }
+        }
+
+        for tid in tids {
+            if self.threads.contains_key(&tid) {
+                continue;
+            }
+            match Counter::new(&self.event, (Proc(tid), Cpu::ALL), &self.opts) {
+                Ok(counter) => {
+                    self.threads.insert(tid, counter);
+                }
+                // The thread exited after listing.
+                Err(e) if e.raw_os_error() == Some(libc::ESRCH) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Enables counters of all threads.
+    pub fn enable(&mut self) -> Result<()> {
+        self.opts.enable = true;
+        self.threads.values().try_for_each(Counter::enable)
+    }
+
+    /// Disables counters of all threads.
+    pub fn disable(&mut self) -> Result<()> {
+        self.opts.enable = false;
+        self.threads.values().try_for_each(Counter::disable)
+    }
+
+    /// Clears the counts of all threads, including the counts of exited threads.
+    pub fn clear_count(&mut self) -> Result<()> {
+        self.exited.clear();
+        self.threads.values().try_for_each(Counter::clear_count)
+    }
+
+    /// Returns the per-thread and process statistics.
+    pub fn stat(&self) -> Result<ProcessStat> {
+        let threads = self
+            .threads
+            .iter()
+            .map(|(tid, counter)| Ok((*tid, counter.stat()?)))
+            .collect::<Result<Vec<_>>>()?;
+        let sum = sum(threads.iter().map(|(_, it)| it).chain(&self.exited));
+        Ok(ProcessStat { threads, sum })
+    }
+}
+
+/// Statistics of a [`ProcessCounter`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProcessStat {
+    /// Statistics by thread ID of the live threads.
+    pub threads: Vec<(u32, Stat)>,
+
+    /// Sum of the statistics of all threads, including exited threads.
+    ///
+    /// See [`SetStat::sum`][super::set::SetStat::sum] for how statistics are summed.
+    pub sum: Stat,
+}
+
+// Thread IDs in `/proc/<pid>/task`.
+fn tids(pid: u32) -> Result<Vec<u32>> {
+    let mut tids = vec![];
+    for entry in read_dir(format!("/proc/{}/task", pid))? {
+        let name = entry?.file_name();
+        if let Some(tid) = name.to_str().and_then(|it| it.parse().ok()) {
+            tids.push(tid);
+        }
+    }
+    Ok(tids)
+}
@@ -0,0 +1,67 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Duration;
+
+use super::ProcessCounter;
+use crate::config::{Opts, Proc};
+use crate::event::sw::Software;
+use crate::test::busy_loop;
+
+#[test]
+fn test_process_counter() {
+    let pid = Proc(std::process::id());
+    let mut counter = ProcessCounter::new(Software::TaskClock, pid, Opts::default()).unwrap();
+    assert_eq!(counter.pid(), pid.0);
+    assert!(!counter.threads().is_empty());
+    counter.enable().unwrap();
+
+    // New thread is picked up by refreshing.
+    let started = Arc::new(Barrier::new(2));
+    let stop = Arc::new(AtomicBool::new(false));
+    let handle = thread::spawn({
+        let started = Arc::clone(&started);
+        let stop = Arc::clone(&stop);
+        move || {
+            started.wait();
+            while !stop.load(Ordering::Relaxed) {
+                busy_loop();
+            }
+            unsafe { libc::gettid() as u32 }
+        }
+    });
+    started.wait();
+    counter.refresh().unwrap();
+    busy_loop();
+
+    stop.store(true, Ordering::Relaxed);
+    let tid = handle.join().unwrap();
+    let stat = counter.stat().unwrap();
+    let thread = stat.threads.iter().find(|(it, _)| *it == tid);
+    assert!(thread.is_some_and(|(_, it)| it.count > 0));
+    let thread_count = thread.unwrap().1.count;
+
+    // Exited thread is removed, but its count is kept in the process totals.
+    // The task may be visible in procfs for a short time after joining.
+    for _ in 0..100 {
+        counter.refresh().unwrap();
+        if !counter.threads().contains_key(&tid) {
+            break;
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    counter.disable().unwrap();
+    assert!(!counter.threads().contains_key(&tid));
+    let stat = counter.stat().unwrap();
+    assert!(stat.threads.iter().all(|(it, _)| *it != tid));
+    assert!(stat.sum.count >= thread_count);
+
+    counter.clear_count().unwrap();
+    assert_eq!(counter.stat().unwrap().sum.count, 0);
+}
+
+#[test]
+fn test_no_process() {
+    let pid = Proc(u32::MAX >> 1);
+    assert!(ProcessCounter::new(Software::TaskClock, pid, Opts::default()).is_err());
+}
@@ -0,0 +1,209 @@
+#[cfg(test)]
+mod test;
+
+use std::borrow::Borrow;
+use std::io::{Error, Result};
+
+use super::group::CounterGroup;
+use super::{Counter, SiblingStat, Stat};
+use crate::config::{sibling, All, Cpu, Opts, Target};
+use crate::event::Event;
+
+/// Counters of the same event (or group) on multiple CPUs.
+///
+/// CPUs that failed to open the event are [reported][Self::failures] instead
+/// of failing the whole set, e.g., CPUs that went offline or isolated cores.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use perf_event_open::config::Opts;
+/// use perf_event_open::count::set::CounterSet;
+/// use perf_event_open::event::sw::Software;
+///
+/// // Count context switches of all processes on all online CPUs.
+/// let set = CounterSet::new(Software::CtxSwitch, Opts::default()).unwrap();
+///
+/// for (cpu, error) in set.failures() {
+///     println!("CPU {}: {}", cpu.0, error);
+/// }
+///
+/// set.enable().unwrap();
+/// thread::sleep(Duration::from_millis(100));
+/// set.disable().unwrap();
+///
+/// let stat = set.stat().unwrap();
+/// for (cpu, stat) in &stat.cpus {
+///     println!("CPU {}: {}", cpu.0, stat.count);
+/// }
+/// println!("Total: {}", stat.sum.count);
+/// ```
+pub struct CounterSet {
+    groups: Vec<(Cpu, CounterGroup)>,
+    failures: Vec<(Cpu, Error)>,
+}
+
+impl CounterSet {
+    /// Opens the event for all processes on every online CPU.
+    pub fn new(event: impl TryInto<Event, Error = Error>, opts: impl Borrow<Opts>) -> Result<Self> {
+        Self::with_target(event, |cpu| (All, cpu).into(), opts)
+    }
+
+    /// Opens the event on every online CPU, with the target returned by `target` for each CPU.
+    ///
+    /// Returns the error of the first CPU if the event can't be opened on any CPU.
+    pub fn with_target(
+        event: impl TryInto<Event, Error = Error>,
+        target: impl FnMut(Cpu) -> Target,
+        opts: impl Borrow<Opts>,
+    ) -> Result<Self> {
+        Self::with_cpus(event, Cpu::online()?, target, opts)
+    }
+
+    /// Opens the event on the given CPUs, with the target returned by `target` for each CPU.
+    ///
+    /// Returns the error of the first CPU if the event can't be opened on any CPU.
+    pub fn with_cpus(
+        event: impl TryInto<Event, Error = Error>,
+        cpus: impl IntoIterator<Item = Cpu>,
+        mut target: impl FnMut(Cpu) -> Target,
+        opts: impl Borrow<Opts>,
+    ) -> Result<Self> {
+        let event = event.try_into()?;
+        let opts = opts.borrow();
+
+        let mut groups = vec![];
+        let mut failures = vec![];
+        for cpu in cpus {
+            match Counter::new(&event, target(cpu), opts) {
+                Ok(leader) => groups.push((cpu, CounterGroup::from(leader))),
+                Err(e) => failures.push((cpu, e)),
+            }
+        }
+
+        if groups.is_empty() && !failures.is_empty() {
+            return Err(failures.swap_remove(0).1);
+        }
+        Ok(Self { groups, failures })
+    }
+
+    /// Adds sibling event to the group on every CPU.
+    ///
+    /// CPUs that failed to open the sibling are removed from the set and
+    /// reported in [`failures`][Self::failures], since their groups would
+    /// be inconsistent with others.
+    pub fn add(
+        &mut self,
+        event: impl TryInto<Event, Error = Error>,
+        opts: impl Borrow<sibling::Opts>,
+    ) -> Result<()> {
+        let event = event.try_into()?;
+        let opts = opts.borrow();
+
+        let mut i = 0;
+        while let Some((cpu, group)) = self.groups.get_mut(i) {
+            match group.add(&event, opts) {
+                Ok(_) => i += 1,
+                Err(e) => {
+                    let cpu = *cpu;
+                    self.groups.remove(i);
+                    self.failures.push((cpu, e));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the counter groups by CPU.
+    ///
+    /// Each group contains only the leader if no sibling was [added][Self::add].
+    pub fn groups(&self) -> &[(Cpu, CounterGroup)] {
+        &self.groups
+    }
+
+    /// Returns the CPUs that failed to open the events and the errors.
+    pub fn failures(&self) -> &[(Cpu, Error)] {
+        &self.failures
+    }
+
+    /// Enables counters on all CPUs.
+    pub fn enable(&self) -> Result<()> {
+        self.groups.iter().try_for_each(|(_, it)| it.enable())
+    }
+
+    /// Disables counters on all CPUs.
+    pub fn disable(&self) -> Result<()> {
+        self.groups.iter().try_for_each(|(_, it)| it.disable())
+    }
+
+    /// Clears the counts of counters on all CPUs.
+    pub fn clear_count(&self) -> Result<()> {
+        self.groups.iter().try_for_each(|(_, it)| it.clear_count())
+    }
+
+    /// Returns the per-CPU and summed statistics.
+    pub fn stat(&self) -> Result<SetStat> {
+        let cpus = self
+            .groups
+            .iter()
+            .map(|(cpu, group)| Ok((*cpu, group.leader().stat()?)))
+            .collect::<Result<Vec<_>>>()?;
+        let sum = sum(cpus.iter().map(|(_, it)| it));
+        Ok(SetStat { cpus, sum })
+    }
+}
+
+/// Statistics of a [`CounterSet`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SetStat {
+    /// Statistics by CPU.
+    pub cpus: Vec<(Cpu, Stat)>,
+
+    /// Sum of the statistics of all CPUs.
+    ///
+    /// Counts, enabled and running times, and lost records are summed, sibling
+    /// counts are summed by their order in the group. IDs are not available.
+    pub sum: Stat,
+}
+
+pub(crate) fn sum<'a>(stats: impl IntoIterator<Item = &'a Stat>) -> Stat {
+    fn add(a: Option<u64>, b: Option<u64>) -> Option<u64> {
+        match (a, b) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+        }
+    }
+
+    let mut sum = Stat {
+        count: 0,
+        id: None,
+        time_enabled: None,
+        time_running: None,
+        lost_records: None,
+        siblings: vec![],
+    };
+    for stat in stats {
+        sum.count += stat.count;
+        sum.time_enabled = add(sum.time_enabled, stat.time_enabled);
+        sum.time_running = add(sum.time_running, stat.time_running);
+        sum.lost_records = add(sum.lost_records, stat.lost_records);
+        for (i, sibling) in stat.siblings.iter().enumerate() {
+            match sum.siblings.get_mut(i) {
+                Some(it) => {
+                    it.count += sibling.count;
+                    it.lost_records = add(it.lost_records, sibling.lost_records);
+                }
+                None => sum.siblings.push(SiblingStat {
+                    count: sibling.count,
+                    id: None,
+                    lost_records: sibling.lost_records,
+                }),
+            }
+        }
+    }
+    sum
+}
@@ -0,0 +1,77 @@
+use super::{sum, CounterSet};
+use crate::config::{Cpu, Opts, Proc};
+use crate::count::{SiblingStat, Stat};
+use crate::event::sw::Software;
+use crate::test::busy_loop;
+
+#[test]
+fn test_sum() {
+    let stat = |count, time, siblings: &[u64]| Stat {
+        count,
+        id: Some(1),
+        time_enabled: Some(time),
+        time_running: Some(time / 2),
+        lost_records: None,
+        siblings: siblings
+            .iter()
+            .map(|it| SiblingStat {
+                count: *it,
+                id: Some(2),
+                lost_records: None,
+            })
+            .collect(),
+    };
+    let sum = sum(&[stat(1, 10, &[4, 5]), stat(2, 20, &[6, 7])]);
+    assert_eq!(sum.count, 3);
+    assert_eq!(sum.id, None);
+    assert_eq!(sum.time_enabled, Some(30));
+    assert_eq!(sum.time_running, Some(15));
+    assert_eq!(sum.lost_records, None);
+    assert_eq!(sum.siblings.len(), 2);
+    assert_eq!(sum.siblings[0].count, 10);
+    assert_eq!(sum.siblings[1].count, 12);
+    assert_eq!(sum.siblings[1].id, None);
+}
+
+#[test]
+fn test_counter_set() {
+    let cpus = Cpu::online().unwrap();
+    assert!(!cpus.is_empty());
+
+    let mut opts = Opts::default();
+    opts.stat_format.siblings = true;
+    let target = |cpu| (Proc::CURRENT, cpu).into();
+    let mut set = CounterSet::with_target(Software::TaskClock, target, opts).unwrap();
+    set.add(Software::TaskClock, crate::config::sibling::Opts::default())
+        .unwrap();
+    assert!(set.failures().is_empty());
+    assert_eq!(set.groups().len(), cpus.len());
+
+    set.enable().unwrap();
+    busy_loop();
+    set.disable().unwrap();
+
+    let stat = set.stat().unwrap();
+    assert_eq!(stat.cpus.len(), cpus.len());
+    assert!(stat.sum.count > 0);
+    assert_eq!(
+        stat.sum.count,
+        stat.cpus.iter().map(|(_, it)| it.count).sum::<u64>()
+    );
+    assert_eq!(stat.sum.siblings.len(), 1);
+}
+
+#[test]
+fn test_failures() {
+    let offline = Cpu(u32::MAX >> 1);
+    let cpus = [Cpu(0), offline];
+    let target = |cpu| (Proc::CURRENT, cpu).into();
+    let set = CounterSet::with_cpus(Software::TaskClock, cpus, target, Opts::default()).unwrap();
+    assert_eq!(set.groups().len(), 1);
+    assert_eq!(set.failures().len(), 1);
+    assert_eq!(set.failures()[0].0, offline);
+
+    let target = |cpu| (Proc::CURRENT, cpu).into();
+    let result = CounterSet::with_cpus(Software::TaskClock, [offline], target, Opts::default());
+    assert!(result.is_err());
+}
@@ -0,0 +1,206 @@
+#[cfg(test)]
+mod test;
+
+use std::ffi::CString;
+use std::fs::File;
+use std::io::Result;
+use std::sync::Arc;
+
+use super::group::CounterGroup;
+use super::{ioctl, Counter, Stat};
+use crate::ffi::syscall;
+
+/// Event counter that can be shared between threads.
+///
+/// [`Counter`] is not `Sync` since [`Counter::stat`] reuses its read buffer.
+/// This type reads into a buffer allocated per call instead, so it can be put
+/// in an [`Arc`] and read from many threads at the same time, e.g., by a
+/// metrics exporter thread while the worker threads keep running.
+///
+/// Operations that modify the counter (such as [`Counter::switch_to`]) are
+/// not available, create the shared counter after the counter is set up.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use perf_event_open::config::{Cpu, Opts, Proc};
+/// use perf_event_open::count::{Counter, SharedCounter};
+/// use perf_event_open::event::sw::Software;
+///
+/// let target = (Proc::CURRENT, Cpu::ALL);
+/// let counter = Counter::new(Software::TaskClock, target, Opts::default()).unwrap();
+/// let counter = Arc::new(SharedCounter::from(counter));
+///
+/// counter.enable().unwrap();
+/// let handles: Vec<_> = (0..4)
+///     .map(|_| {
+///         let counter = Arc::clone(&counter);
+///         thread::spawn(move || counter.stat().unwrap().count)
+///     })
+///     .collect();
+/// for handle in handles {
+///     println!("{} ns", handle.join().unwrap());
+/// }
+/// ```
+pub struct SharedCounter {
+    perf: Arc<File>,
+    read_format: u64,
+    group_size: usize,
+    // Keep strings pointed by the event config alive, see `Counter::keep_alive`.
+    #[allow(dead_code)]
+    keep_alive: Option<Arc<CString>>,
+}
+
+impl SharedCounter {
+    fn new(counter: &Counter, group_size: usize) -> Self {
+        // The counter is owned by the caller, there is no other reference to
+        // the attr and `keep_alive` at the same time.
+        let read_format = unsafe { &*counter.attr.get() }.read_format;
+        let keep_alive = unsafe { &*counter.keep_alive.get() }.clone();
+        Self {
+            perf: Arc::clone(&counter.perf),
+            read_format,
+            group_size,
+            keep_alive,
+        }
+    }
+
+    /// Returns the file handle opened by [`perf_event_open`](https://man7.org/linux/man-pages/man2/perf_event_open.2.html)
+    /// system call for the current event.
+    pub fn file(&self) -> &File {
+        &self.perf
+    }
+
+    /// Returns the event ID.
+    ///
+    /// See [`Counter::id`] for details.
+    pub fn id(&self) -> Result<u64> {
+        ioctl::id(&self.perf)
+    }
+
+    /// Enable counter.
+    pub fn enable(&self) -> Result<()> {
+        ioctl::enable(&self.perf, false)
+    }
+
+    /// Disable counter.
+    pub fn disable(&self) -> Result<()> {
+        ioctl::disable(&self.perf, false)
+    }
+
+    /// Clear event count.
+    ///
+    /// See [`Counter::clear_count`] for details.
+    pub fn clear_count(&self) -> Result<()> {
+        ioctl::reset(&self.perf, false)
+    }
+
+    /// Returns counter statistics.
+    ///
+    /// This is the same as [`Counter::stat`], but can be called from multiple
+    /// threads at the same time.
+    pub fn stat(&self) -> Result<Stat> {
+        // `Stat` allocates for siblings anyway, so the buffer is allocated per
+        // call instead of synchronizing a shared one.
+        let mut buf = vec![0; Stat::read_buf_size(self.group_size, self.read_format)];
+        syscall!(read, &self.perf, &mut buf)?;
+        let stat = unsafe { Stat::from_ptr(buf.as_ptr(), self.read_format) };
+        Ok(stat)
+    }
+}
+
+impl From<Counter> for SharedCounter {
+    fn from(counter: Counter) -> Self {
+        Self::new(&counter, 1)
+    }
+}
+
+/// Counter group that can be shared between threads.
+///
+/// This is the thread-safe version of [`CounterGroup`], see [`SharedCounter`]
+/// for details. Siblings can't be added after the group becomes shared.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use perf_event_open::config::{Cpu, Opts, Proc};
+/// use perf_event_open::count::group::CounterGroup;
+/// use perf_event_open::count::{Counter, SharedGroup};
+/// use perf_event_open::event::sw::Software;
+///
+/// let target = (Proc::CURRENT, Cpu::ALL);
+/// let mut opts = Opts::default();
+/// opts.stat_format.siblings = true;
+///
+/// let leader = Counter::new(Software::TaskClock, target, opts).unwrap();
+/// let mut group = CounterGroup::from(leader);
+/// group.add(Software::PageFault, &Default::default()).unwrap();
+/// let group = Arc::new(SharedGroup::from(group));
+///
+/// group.enable().unwrap();
+/// let exporter = thread::spawn({
+///     let group = Arc::clone(&group);
+///     move || group.stat().unwrap()
+/// });
+/// let stat = exporter.join().unwrap();
+/// println!("{} ns, {} page faults", stat.count, stat.siblings[0].count);
+/// ```
+pub struct SharedGroup {
+    leader: SharedCounter,
+    siblings: Vec<SharedCounter>,
+}
+
+impl SharedGroup {
+    /// Returns a reference to the leader of the counter group.
+    pub fn leader(&self) -> &SharedCounter {
+        &self.leader
+    }
+
+    /// Returns the sibling counters of the counter group in the order they were added.
+    pub fn siblings(&self) -> &[SharedCounter] {
+        &self.siblings
+    }
+
+    /// Enables all counters in the group.
+    pub fn enable(&self) -> Result<()> {
+        ioctl::enable(&self.leader.perf, true)
+    }
+
+    /// Disables all counters in the group.
+    pub fn disable(&self) -> Result<()> {
+        ioctl::disable(&self.leader.perf, true)
+    }
+
+    /// Clears the counts of all counters in the group.
+    pub fn clear_count(&self) -> Result<()> {
+        ioctl::reset(&self.leader.perf, true)
+    }
+
+    /// Returns the leader statistics.
+    ///
+    /// Sibling counts are included if the leader was created with
+    /// [`StatFormat::siblings`][crate::config::StatFormat::siblings].
+    pub fn stat(&self) -> Result<Stat> {
+        self.leader.stat()
+    }
+}
+
+impl From<CounterGroup> for SharedGroup {
+    fn from(group: CounterGroup) -> Self {
+        let group_size = group.siblings().len() + 1;
+        Self {
+            leader: SharedCounter::new(group.leader(), group_size),
+            siblings: group
+                .siblings()
+                .iter()
+                .map(|it| SharedCounter::new(it, 1))
+                .collect(),
+        }
+    }
+}
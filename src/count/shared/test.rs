@@ -0,0 +1,72 @@
+use std::sync::Arc;
+use std::thread;
+
+use super::{SharedCounter, SharedGroup};
+use crate::config::{Cpu, Opts, Proc};
+use crate::count::group::CounterGroup;
+use crate::count::Counter;
+use crate::event::sw::Software;
+
+fn is_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_send_sync() {
+    is_send_sync::<SharedCounter>();
+    is_send_sync::<SharedGroup>();
+}
+
+#[test]
+fn test_counter() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let counter = Counter::new(Software::TaskClock, target, Opts::default()).unwrap();
+    let id = counter.id().unwrap();
+    let counter = Arc::new(SharedCounter::from(counter));
+    assert_eq!(counter.id().unwrap(), id);
+
+    counter.enable().unwrap();
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                (0..100)
+                    .map(|_| counter.stat().unwrap().count)
+                    .max()
+                    .unwrap()
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert!(handle.join().unwrap() > 0);
+    }
+
+    counter.disable().unwrap();
+    counter.clear_count().unwrap();
+    assert_eq!(counter.stat().unwrap().count, 0);
+}
+
+#[test]
+fn test_group() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let mut opts = Opts::default();
+    opts.stat_format.siblings = true;
+    let leader = Counter::new(Software::TaskClock, target, opts).unwrap();
+    let mut group = CounterGroup::from(leader);
+    for _ in 0..2 {
+        group
+            .add(Software::TaskClock, crate::config::sibling::Opts::default())
+            .unwrap();
+    }
+    let group = Arc::new(SharedGroup::from(group));
+    assert_eq!(group.siblings().len(), 2);
+
+    group.enable().unwrap();
+    let stat = thread::spawn({
+        let group = Arc::clone(&group);
+        move || group.stat().unwrap()
+    })
+    .join()
+    .unwrap();
+    assert!(stat.count > 0);
+    assert_eq!(stat.siblings.len(), 2);
+    assert!(group.siblings()[0].stat().unwrap().count > 0);
+}
@@ -0,0 +1,326 @@
+#[cfg(test)]
+mod test;
+
+use std::borrow::Borrow;
+use std::io::{Error, ErrorKind, Result};
+
+use super::group::CounterGroup;
+use super::{Counter, Stat};
+use crate::config::{sibling, Opts, Target};
+use crate::event::dp::Pmu;
+use crate::event::hw::Hardware;
+use crate::event::Event;
+
+// Intel core PMU, `cpu_core` is the P-core PMU on hybrid CPUs.
+const CORE_PMUS: [&str; 2] = ["cpu", "cpu_core"];
+
+const LEVEL1_EVENTS: [&str; 4] = [
+    "topdown-retiring",
+    "topdown-bad-spec",
+    "topdown-fe-bound",
+    "topdown-be-bound",
+];
+
+const LEVEL2_EVENTS: [&str; 4] = [
+    "topdown-heavy-ops",
+    "topdown-br-mispredict",
+    "topdown-fetch-lat",
+    "topdown-mem-bound",
+];
+
+/// Top-down microarchitecture analysis (TMA) counters.
+///
+/// On Intel CPUs with the `PERF_METRICS` MSR (Ice Lake and later), the `slots`
+/// event is opened as group leader with the `topdown-*` events as siblings.
+/// The kernel decodes `PERF_METRICS` into slot counts of the `topdown-*` events,
+/// which are used to compute the level 1 metrics, and the level 2 metrics if
+/// the CPU supports them (Sapphire Rapids, Alder Lake P-cores and later).
+///
+/// On other CPUs, the generic [cycle][Hardware::CpuCycle],
+/// [frontend stall][Hardware::FrontendStalledCycle] and
+/// [backend stall][Hardware::BackendStalledCycle] events are used instead.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use perf_event_open::config::{Cpu, Opts, Proc};
+/// use perf_event_open::count::topdown::Topdown;
+///
+/// let target = (Proc::ALL, Cpu(0)); // All processes on CPU 0.
+///
+/// let Ok(topdown) = Topdown::new(target, Opts::default()) else {
+///     return; // Not supported by the CPU.
+/// };
+///
+/// topdown.group().enable().unwrap();
+/// thread::sleep(Duration::from_millis(100));
+/// topdown.group().disable().unwrap();
+///
+/// let stat = topdown.stat().unwrap();
+/// println!("{:#?}", stat.level1);
+/// ```
+pub struct Topdown {
+    group: CounterGroup,
+    method: Method,
+}
+
+/// How the top-down metrics are measured.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Method {
+    /// Intel `slots` and `topdown-*` events.
+    Slots {
+        /// Whether the level 2 events are available.
+        level2: bool,
+    },
+    /// Generic cycle and stalled cycle events.
+    Generic,
+}
+
+impl Topdown {
+    /// Creates the top-down counters, using the slots method if possible.
+    ///
+    /// Falls back to the generic method only if the slots events are not supported
+    /// ([`ErrorKind::Unsupported`] or [`ErrorKind::NotFound`]), other errors
+    /// (e.g., permission denied or too many open files) are returned.
+    ///
+    /// [Sibling counts][crate::config::StatFormat::siblings] are always enabled.
+    pub fn new(target: impl Into<Target>, opts: impl Borrow<Opts>) -> Result<Self> {
+        let target = target.into();
+        let opts = opts.borrow();
+        match Self::with_slots(target.clone(), opts) {
+            Err(e) if matches!(e.kind(), ErrorKind::Unsupported | ErrorKind::NotFound) => {
+                Self::with_generic(target, opts)
+            }
+            result => result,
+        }
+    }
+
+    /// Creates the top-down counters with Intel `slots` and `topdown-*` events.
+    pub fn with_slots(target: impl Into<Target>, opts: impl Borrow<Opts>) -> Result<Self> {
+        let pmu = CORE_PMUS
+            .iter()
+            .filter_map(|it| Pmu::from_name(it).ok())
+            .find(|it| {
+                let mut events = ["slots"].iter().chain(LEVEL1_EVENTS.iter());
+                events.all(|name| it.events.contains_key(*name))
+            })
+            .ok_or_else(|| {
+                let error = "No core PMU with `slots` and `topdown-*` events";
+                Error::new(ErrorKind::Unsupported, error)
+            })?;
+        let level2 = LEVEL2_EVENTS.iter().all(|it| pmu.events.contains_key(*it));
+
+        let mut events = vec![];
+        events.extend(LEVEL1_EVENTS);
+        if level2 {
+            events.extend(LEVEL2_EVENTS);
+        }
+        let events = events
+            .into_iter()
+            .map(|it| pmu.event(it))
+            .collect::<Result<Vec<_>>>()?;
+
+        // The kernel rejects `topdown-*` events unless the slots event is the group leader.
+        let group = Self::open(pmu.event("slots")?, events, target, opts)?;
+        Ok(Self {
+            group,
+            method: Method::Slots { level2 },
+        })
+    }
+
+    /// Creates the top-down counters with generic cycle and stalled cycle events.
+    pub fn with_generic(target: impl Into<Target>, opts: impl Borrow<Opts>) -> Result<Self> {
+        let group = Self::open(
+            Hardware::CpuCycle,
+            [
+                Hardware::FrontendStalledCycle,
+                Hardware::BackendStalledCycle,
+            ],
+            target,
+            opts,
+        )?;
+        Ok(Self {
+            group,
+            method: Method::Generic,
+        })
+    }
+
+    fn open<L, S>(
+        leader: L,
+        siblings: impl IntoIterator<Item = S>,
+        target: impl Into<Target>,
+        opts: impl Borrow<Opts>,
+    ) -> Result<CounterGroup>
+    where
+        L: TryInto<Event, Error = Error>,
+        S: TryInto<Event, Error = Error>,
+    {
+        let mut opts = opts.borrow().clone();
+        opts.stat_format.siblings = true;
+        let sibling_opts = sibling::Opts {
+            exclude: opts.exclude.clone(),
+            inherit: opts.inherit,
+            on_execve: opts.on_execve,
+            ..Default::default()
+        };
+
+        let leader = Counter::new(leader, target, &opts)?;
+        let mut group = CounterGroup::from(leader);
+        for sibling in siblings {
+            group.add(sibling, &sibling_opts)?;
+        }
+        Ok(group)
+    }
+
+    /// Returns the counter group, which can be used to enable or disable the counters.
+    pub fn group(&self) -> &CounterGroup {
+        &self.group
+    }
+
+    /// Returns the method used to measure the metrics.
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    /// Reads the counters and computes the metrics.
+    pub fn stat(&self) -> Result<TopdownStat> {
+        let stat = self.group.leader().stat()?;
+        let counts: Vec<_> = stat.siblings.iter().map(|it| it.count).collect();
+
+        let (level1, level2) = match self.method {
+            Method::Slots { level2 } => {
+                let level1 = Level1::from_slots(counts[..4].try_into().unwrap());
+                let level2 = level2.then(|| {
+                    let total = counts[..4].iter().sum();
+                    Level2::from_slots(&level1, total, counts[4..8].try_into().unwrap())
+                });
+                (level1, level2)
+            }
+            Method::Generic => {
+                let level1 = Level1::from_cycles(stat.count, counts[0], counts[1]);
+                (level1, None)
+            }
+        };
+
+        Ok(TopdownStat {
+            level1,
+            level2,
+            stat,
+        })
+    }
+}
+
+/// Top-down metrics.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TopdownStat {
+    /// Level 1 metrics.
+    pub level1: Level1,
+    /// Level 2 metrics, only available with [`Method::Slots`] on supported CPUs.
+    pub level2: Option<Level2>,
+    /// Raw statistics of the group, with the leader (`slots` or cycles)
+    /// count and the sibling counts in the order of the events.
+    pub stat: Stat,
+}
+
+/// Level 1 top-down metrics, as fractions of the pipeline slots (or cycles).
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Level1 {
+    /// Slots retiring operations.
+    ///
+    /// With [`Method::Generic`], this also includes the bad speculation,
+    /// which is all cycles not stalled by the frontend or backend.
+    pub retiring: f64,
+    /// Slots wasted due to incorrect speculation.
+    ///
+    /// Not available with [`Method::Generic`].
+    pub bad_speculation: Option<f64>,
+    /// Slots stalled due to the frontend not supplying operations.
+    pub frontend_bound: f64,
+    /// Slots stalled due to the backend lacking resources.
+    pub backend_bound: f64,
+}
+
+impl Level1 {
+    // The sum of all topdown slots is used as the total slots, same as perf.
+    pub(crate) fn from_slots([retiring, bad_spec, fe_bound, be_bound]: [u64; 4]) -> Self {
+        let total = retiring + bad_spec + fe_bound + be_bound;
+        Self {
+            retiring: ratio(retiring, total),
+            bad_speculation: Some(ratio(bad_spec, total)),
+            frontend_bound: ratio(fe_bound, total),
+            backend_bound: ratio(be_bound, total),
+        }
+    }
+
+    pub(crate) fn from_cycles(cycles: u64, fe_stalled: u64, be_stalled: u64) -> Self {
+        let frontend_bound = ratio(fe_stalled, cycles);
+        let backend_bound = ratio(be_stalled, cycles);
+        Self {
+            retiring: (1.0 - frontend_bound - backend_bound).max(0.0),
+            bad_speculation: None,
+            frontend_bound,
+            backend_bound,
+        }
+    }
+}
+
+/// Level 2 top-down metrics, as fractions of the pipeline slots.
+///
+/// Each pair of metrics breaks down one of the [level 1 metrics][Level1].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Level2 {
+    /// Retiring heavy operations (e.g., microcode sequences).
+    pub heavy_operations: f64,
+    /// Retiring light operations.
+    pub light_operations: f64,
+    /// Bad speculation due to branch mispredictions.
+    pub branch_mispredicts: f64,
+    /// Bad speculation due to machine clears.
+    pub machine_clears: f64,
+    /// Frontend bound due to fetch latency (e.g., instruction cache misses).
+    pub fetch_latency: f64,
+    /// Frontend bound due to fetch bandwidth.
+    pub fetch_bandwidth: f64,
+    /// Backend bound due to the memory subsystem.
+    pub memory_bound: f64,
+    /// Backend bound due to the execution units.
+    pub core_bound: f64,
+}
+
+impl Level2 {
+    pub(crate) fn from_slots(
+        level1: &Level1,
+        total: u64,
+        [heavy_ops, br_mispredict, fetch_lat, mem_bound]: [u64; 4],
+    ) -> Self {
+        let heavy_operations = ratio(heavy_ops, total);
+        let branch_mispredicts = ratio(br_mispredict, total);
+        let fetch_latency = ratio(fetch_lat, total);
+        let memory_bound = ratio(mem_bound, total);
+        let bad_speculation = level1.bad_speculation.unwrap_or_default();
+        Self {
+            heavy_operations,
+            light_operations: (level1.retiring - heavy_operations).max(0.0),
+            branch_mispredicts,
+            machine_clears: (bad_speculation - branch_mispredicts).max(0.0),
+            fetch_latency,
+            fetch_bandwidth: (level1.frontend_bound - fetch_latency).max(0.0),
+            memory_bound,
+            core_bound: (level1.backend_bound - memory_bound).max(0.0),
+        }
+    }
+}
+
+fn ratio(part: u64, total: u64) -> f64 {
+    match total {
+        0 => 0.0,
+        total => part as f64 / total as f64,
+    }
+}
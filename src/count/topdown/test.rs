@@ -0,0 +1,78 @@
+use super::{Level1, Level2, Method, Topdown};
+use crate::config::{Cpu, Opts, Proc};
+use crate::test::busy_loop;
+
+#[test]
+fn test_level1_from_slots() {
+    let level1 = Level1::from_slots([400, 100, 200, 300]);
+    assert_eq!(level1.retiring, 0.4);
+    assert_eq!(level1.bad_speculation, Some(0.1));
+    assert_eq!(level1.frontend_bound, 0.2);
+    assert_eq!(level1.backend_bound, 0.3);
+
+    let level1 = Level1::from_slots([0; 4]);
+    assert_eq!(level1.retiring, 0.0);
+    assert_eq!(level1.bad_speculation, Some(0.0));
+}
+
+#[test]
+fn test_level2_from_slots() {
+    let level1 = Level1::from_slots([400, 100, 200, 300]);
+    let level2 = Level2::from_slots(&level1, 1000, [100, 50, 150, 200]);
+    assert_eq!(level2.heavy_operations, 0.1);
+    assert!((level2.light_operations - 0.3).abs() < 1e-9);
+    assert_eq!(level2.branch_mispredicts, 0.05);
+    assert!((level2.machine_clears - 0.05).abs() < 1e-9);
+    assert_eq!(level2.fetch_latency, 0.15);
+    assert!((level2.fetch_bandwidth - 0.05).abs() < 1e-9);
+    assert_eq!(level2.memory_bound, 0.2);
+    assert!((level2.core_bound - 0.1).abs() < 1e-9);
+
+    // Sub-metrics larger than their parent are clamped.
+    let level2 = Level2::from_slots(&level1, 1000, [500, 0, 0, 0]);
+    assert_eq!(level2.light_operations, 0.0);
+}
+
+#[test]
+fn test_level1_from_cycles() {
+    let level1 = Level1::from_cycles(1000, 250, 500);
+    assert_eq!(level1.frontend_bound, 0.25);
+    assert_eq!(level1.backend_bound, 0.5);
+    assert_eq!(level1.retiring, 0.25);
+    assert_eq!(level1.bad_speculation, None);
+
+    let level1 = Level1::from_cycles(1000, 800, 800);
+    assert_eq!(level1.retiring, 0.0);
+
+    let level1 = Level1::from_cycles(0, 0, 0);
+    assert_eq!(level1.frontend_bound, 0.0);
+}
+
+#[test]
+fn test_topdown() {
+    let Ok(topdown) = Topdown::new((Proc::CURRENT, Cpu::ALL), Opts::default()) else {
+        return; // No hardware PMU.
+    };
+
+    topdown.group().enable().unwrap();
+    busy_loop();
+    topdown.group().disable().unwrap();
+
+    let stat = topdown.stat().unwrap();
+    let level1 = stat.level1;
+    match topdown.method() {
+        Method::Slots { level2 } => {
+            let sum = level1.retiring
+                + level1.bad_speculation.unwrap()
+                + level1.frontend_bound
+                + level1.backend_bound;
+            assert!(sum == 0.0 || (sum - 1.0).abs() < 1e-9);
+            assert_eq!(stat.level2.is_some(), level2);
+            assert_eq!(stat.stat.siblings.len(), if level2 { 8 } else { 4 });
+        }
+        Method::Generic => {
+            assert_eq!(stat.level2, None);
+            assert_eq!(stat.stat.siblings.len(), 2);
+        }
+    }
+}
@@ -103,6 +103,7 @@ super::try_from!(Breakpoint, value, {
         config2: bp_len,
         config3: 0,
         bp_type,
+        keep_alive: None,
     };
     Ok(Self(event_cfg))
 });
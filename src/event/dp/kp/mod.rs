@@ -1,14 +1,14 @@
 #[cfg(test)]
 mod test;
 
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 use std::io::Result;
+use std::sync::Arc;
 
-use super::{get_retprobe_bit, get_type, DynamicPmu, Error};
+use super::{with_keep_alive, DynamicPmu, Error, Pmu};
 use crate::event::Event;
 
-const TYPE_PATH: &str = "/sys/bus/event_source/devices/kprobe/type";
-const RETPROBE_PATH: &str = "/sys/bus/event_source/devices/kprobe/format/retprobe";
+const PMU_NAME: &str = "kprobe";
 
 /// Kernel probe event
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -21,24 +21,10 @@ pub enum Kprobe {
 
 impl Kprobe {
     pub fn try_into_dp(self) -> Result<DynamicPmu> {
-        let ty = get_type(TYPE_PATH)?;
-        let ev = match self {
-            Kprobe::Symbol { name, offset } => DynamicPmu {
-                ty,
-                config: 0,
-                config1: name.as_ptr() as _,
-                config2: offset,
-                config3: 0,
-            },
-            Kprobe::Addr(addr) => DynamicPmu {
-                ty,
-                config: 0,
-                config1: 0,
-                config2: addr,
-                config3: 0,
-            },
-        };
-        Ok(ev)
+        match self {
+            Kprobe::Symbol { name, offset } => to_dp(false, Some(name), offset),
+            Kprobe::Addr(addr) => to_dp(false, None, addr),
+        }
     }
 }
 
@@ -69,25 +55,10 @@ pub enum Kretprobe {
 
 impl Kretprobe {
     pub fn try_into_dp(self) -> Result<DynamicPmu> {
-        let ty = get_type(TYPE_PATH)?;
-        let retprobe_bit = get_retprobe_bit(RETPROBE_PATH)?;
-        let ev = match self {
-            Kretprobe::Symbol { name, offset } => DynamicPmu {
-                ty,
-                config: 1 << retprobe_bit,
-                config1: name.as_ptr() as _,
-                config2: offset,
-                config3: 0,
-            },
-            Kretprobe::Addr(addr) => DynamicPmu {
-                ty,
-                config: 1 << retprobe_bit,
-                config1: 0,
-                config2: addr,
-                config3: 0,
-            },
-        };
-        Ok(ev)
+        match self {
+            Kretprobe::Symbol { name, offset } => to_dp(true, Some(name), offset),
+            Kretprobe::Addr(addr) => to_dp(true, None, addr),
+        }
     }
 }
 
@@ -106,3 +77,90 @@ impl TryFrom<Kretprobe> for Event {
         value.try_into_dp()?.try_into()
     }
 }
+
+/// Kernel probe event with owned symbol name.
+///
+/// Unlike [`Kprobe`], the symbol name can be chosen at runtime, it will be kept
+/// alive within the [`Event`] and the [`Counter`][crate::count::Counter] opened with it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OwnedKprobe {
+    /// Symbol + offset where the probe is inserted.
+    Symbol { name: CString, offset: u64 },
+    /// Address where the probe is inserted.
+    Addr(u64),
+}
+
+impl TryFrom<&OwnedKprobe> for Event {
+    type Error = Error;
+
+    fn try_from(value: &OwnedKprobe) -> Result<Self> {
+        match value {
+            OwnedKprobe::Symbol { name, offset } => {
+                let name = Arc::new(name.clone());
+                with_keep_alive(to_dp(false, Some(&name), *offset)?, name)
+            }
+            OwnedKprobe::Addr(addr) => to_dp(false, None, *addr)?.try_into(),
+        }
+    }
+}
+
+impl TryFrom<OwnedKprobe> for Event {
+    type Error = Error;
+
+    fn try_from(value: OwnedKprobe) -> Result<Self> {
+        (&value).try_into()
+    }
+}
+
+/// Kernel return probe event with owned symbol name.
+///
+/// Unlike [`Kretprobe`], the symbol name can be chosen at runtime, it will be kept
+/// alive within the [`Event`] and the [`Counter`][crate::count::Counter] opened with it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OwnedKretprobe {
+    /// Symbol + offset where the probe is inserted.
+    Symbol { name: CString, offset: u64 },
+    /// Address where the probe is inserted.
+    Addr(u64),
+}
+
+impl TryFrom<&OwnedKretprobe> for Event {
+    type Error = Error;
+
+    fn try_from(value: &OwnedKretprobe) -> Result<Self> {
+        match value {
+            OwnedKretprobe::Symbol { name, offset } => {
+                let name = Arc::new(name.clone());
+                with_keep_alive(to_dp(true, Some(&name), *offset)?, name)
+            }
+            OwnedKretprobe::Addr(addr) => to_dp(true, None, *addr)?.try_into(),
+        }
+    }
+}
+
+impl TryFrom<OwnedKretprobe> for Event {
+    type Error = Error;
+
+    fn try_from(value: OwnedKretprobe) -> Result<Self> {
+        (&value).try_into()
+    }
+}
+
+// `config2` is the offset to the symbol, or the address if there is no symbol.
+fn to_dp(retprobe: bool, name: Option<&CStr>, config2: u64) -> Result<DynamicPmu> {
+    let pmu = Pmu::formats_of(PMU_NAME)?;
+    let config = match retprobe {
+        true => pmu.format("retprobe")?.encode(1)?,
+        false => 0,
+    };
+    let ev = DynamicPmu {
+        ty: pmu.ty,
+        config,
+        config1: name.map_or(0, |it| it.as_ptr() as _),
+        config2,
+        config3: 0,
+    };
+    Ok(ev)
+}
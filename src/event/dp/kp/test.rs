@@ -1,5 +1,6 @@
-use super::{Kprobe, Kretprobe};
+use super::{Kprobe, Kretprobe, OwnedKprobe};
 use crate::event::dp::DynamicPmu;
+use crate::event::Event;
 
 #[test]
 fn test_from_kprobe_func() {
@@ -30,3 +31,14 @@ fn test_from_kretprobe_addr() {
     let ev = Kretprobe::Addr(0);
     DynamicPmu::try_from(ev).unwrap();
 }
+
+#[test]
+fn test_from_owned_kprobe() {
+    let ev = OwnedKprobe::Symbol {
+        name: c"do_sys_open".into(),
+        offset: 0,
+    };
+    let Event(event_cfg) = Event::try_from(&ev).unwrap();
+    let name = event_cfg.keep_alive.unwrap();
+    assert_eq!(event_cfg.config1, name.as_ptr() as u64);
+}
@@ -1,15 +1,17 @@
 mod kp;
+mod pmu;
 mod up;
 
-use std::fs::File;
-use std::io::{Error, Read, Result, Seek, SeekFrom};
-use std::path::Path;
+use std::ffi::CString;
+use std::io::{Error, Result};
+use std::sync::Arc;
 
 pub use kp::*;
+pub use pmu::*;
 use thiserror::Error;
 pub use up::*;
 
-use super::EventConfig;
+use super::{Event, EventConfig};
 
 /// Dynamic PMU event
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -21,6 +23,8 @@ pub struct DynamicPmu {
     ///
     /// For instance, `/sys/bus/event_source/devices/cpu/type` contains the value for
     /// the core CPU PMU, which is usually 4.
+    ///
+    /// See also [`Pmu`] to discover PMUs and encode their events.
     pub ty: u32,
     /// Event config.
     pub config: u64,
@@ -34,47 +38,6 @@ pub struct DynamicPmu {
     pub config3: u64,
 }
 
-fn get_type<P>(path: P) -> Result<u32>
-where
-    P: AsRef<Path>,
-{
-    let mut file = File::open(path)?;
-
-    let mut acc = Vec::with_capacity(1);
-    let mut buf = [0];
-    while file.read(&mut buf)? > 0 {
-        if buf[0] == b'\n' {
-            break;
-        }
-        acc.extend(buf);
-    }
-    let bit = unsafe { std::str::from_utf8_unchecked(&acc) };
-
-    bit.parse::<u32>().map_err(Error::other)
-}
-
-// bpf_get_retprobe_bit:
-// https://github.com/torvalds/linux/blob/v6.13/samples/bpf/task_fd_query_user.c#L69
-fn get_retprobe_bit<P>(path: P) -> Result<u8>
-where
-    P: AsRef<Path>,
-{
-    let mut file = File::open(path)?;
-    file.seek(SeekFrom::Start("config:".len() as _))?;
-
-    let mut acc = Vec::with_capacity(1);
-    let mut buf = [0];
-    while file.read(&mut buf)? > 0 {
-        if buf[0] == b'\n' {
-            break;
-        }
-        acc.extend(buf);
-    }
-    let bit = unsafe { std::str::from_utf8_unchecked(&acc) };
-
-    bit.parse::<u8>().map_err(Error::other)
-}
-
 super::try_from!(DynamicPmu, value, {
     let event_cfg = EventConfig {
         ty: value.ty,
@@ -83,6 +46,15 @@ super::try_from!(DynamicPmu, value, {
         config2: value.config2,
         config3: value.config3,
         bp_type: 0,
+        keep_alive: None,
     };
     Ok(Self(event_cfg))
 });
+
+// The kernel reads the string pointed by `config1` when opening the event,
+// so the owned probe events keep the string alive within the event.
+fn with_keep_alive(ev: DynamicPmu, keep_alive: Arc<CString>) -> Result<Event> {
+    let Event(mut event_cfg) = ev.try_into()?;
+    event_cfg.keep_alive = Some(keep_alive);
+    Ok(Event(event_cfg))
+}
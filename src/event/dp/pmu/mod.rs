@@ -0,0 +1,431 @@
+#[cfg(test)]
+mod test;
+
+use std::collections::BTreeMap;
+use std::fs::{self, read_dir};
+use std::io::{Error, ErrorKind, Result};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use super::DynamicPmu;
+
+const DEVICES_PATH: &str = "/sys/bus/event_source/devices";
+
+/// PMU instance discovered from sysfs.
+///
+/// Every PMU registered in the kernel has a subdirectory under
+/// `/sys/bus/event_source/devices`, which describes how to encode
+/// its events into [`DynamicPmu`].
+///
+/// # Examples
+///
+/// ```rust
+/// use perf_event_open::event::dp::Pmu;
+///
+/// for pmu in Pmu::all().unwrap() {
+///     println!("{} (type {})", pmu.name, pmu.ty);
+///     for (name, terms) in &pmu.events {
+///         println!("  {}/{}/ -> {}", pmu.name, name, terms);
+///     }
+/// }
+/// ```
+///
+/// Events can be encoded from the term list used by `perf`:
+///
+/// ```rust
+/// use perf_event_open::event::dp::Pmu;
+///
+/// // Intel core PMU
+/// let Ok(cpu) = Pmu::from_name("cpu") else {
+///     return;
+/// };
+/// # if !cpu.formats.contains_key("cmask") {
+/// #     return;
+/// # }
+/// let ev = cpu.encode("event=0xd1,umask=0x20,cmask=1").unwrap();
+/// assert_eq!(ev.config, 0x1_00_20_d1);
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pmu {
+    /// PMU name (e.g., `cpu`, `uprobe` or `uncore_imc_0`).
+    pub name: String,
+
+    /// Dynamic PMU type, same as [`DynamicPmu::ty`].
+    pub ty: u32,
+
+    /// Format fields from `format/*`, keyed by term name.
+    ///
+    /// Each field describes which bits of which config word a term occupies.
+    pub formats: BTreeMap<String, Format>,
+
+    /// Event aliases from `events/*`, keyed by event name.
+    ///
+    /// The value is a term list (e.g., `event=0x3c,umask=0x01`) that can be
+    /// encoded with [`Pmu::encode`].
+    pub events: BTreeMap<String, String>,
+
+    /// PMU capabilities from `caps/*` (e.g., `max_precise` or `pmu_name`).
+    pub caps: BTreeMap<String, String>,
+}
+
+impl Pmu {
+    /// Returns all PMUs registered in the kernel, sorted by name.
+    ///
+    /// PMUs whose sysfs directory can't be read or parsed (e.g., with an
+    /// unknown format) are skipped, they can still be inspected with
+    /// [`Pmu::from_name`] to get the error.
+    pub fn all() -> Result<Vec<Self>> {
+        let mut pmus = vec![];
+        for entry in read_dir(DEVICES_PATH)? {
+            let Ok(entry) = entry else {
+                continue;
+            };
+            if let Ok(pmu) = Self::from_path(&entry.path()) {
+                pmus.push(pmu);
+            }
+        }
+        pmus.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(pmus)
+    }
+
+    /// Returns the PMU with the given name.
+    pub fn from_name(name: &str) -> Result<Self> {
+        Self::from_path(&dir(name)?)
+    }
+
+    // Reads only `type` and `format/*` of the PMU, leaving event aliases and
+    // capabilities empty. Used by probe events, which are created much more
+    // often than PMUs are inspected.
+    pub(crate) fn formats_of(name: &str) -> Result<Self> {
+        let path = dir(name)?;
+        Ok(Self {
+            name: name.to_string(),
+            ty: read_type(&path, name)?,
+            formats: read_formats(&path, name)?,
+            events: BTreeMap::new(),
+            caps: BTreeMap::new(),
+        })
+    }
+
+    // Reads only `type` of all PMUs, returning their names and types.
+    // Used to diagnose counter errors, which only need to find the PMU by type.
+    pub(crate) fn types() -> Result<Vec<(String, u32)>> {
+        let mut types = vec![];
+        for entry in read_dir(DEVICES_PATH)? {
+            let Ok(path) = entry.map(|it| it.path()) else {
+                continue;
+            };
+            let ty = name_of(&path).and_then(|name| Ok((read_type(&path, &name)?, name)));
+            if let Ok((ty, name)) = ty {
+                types.push((name, ty));
+            }
+        }
+        types.sort();
+        Ok(types)
+    }
+
+    // Reads a single capability (e.g., `max_precise`) of the PMU.
+    pub(crate) fn cap_of(name: &str, cap: &str) -> Result<String> {
+        read_trimmed(&dir(name)?.join("caps").join(cap))
+    }
+
+    fn from_path(path: &Path) -> Result<Self> {
+        let name = name_of(path)?;
+
+        let ty = read_type(path, &name)?;
+        let formats = read_formats(path, &name)?;
+
+        let mut events = read_entries(&path.join("events"))?;
+        // Files such as `<event>.scale` and `<event>.unit` describe how to
+        // interpret the counts, they are not event aliases.
+        events.retain(|name, _| !name.contains('.'));
+
+        let caps = read_entries(&path.join("caps"))?;
+
+        Ok(Self {
+            name,
+            ty,
+            formats,
+            events,
+            caps,
+        })
+    }
+
+    /// Returns the format field of the given term name.
+    pub fn format(&self, term: &str) -> Result<&Format> {
+        self.formats.get(term).ok_or_else(|| {
+            let error = format!("PMU `{}` has no format `{}`", self.name, term);
+            Error::new(ErrorKind::NotFound, error)
+        })
+    }
+
+    /// Encodes the event alias from `events/*` into dynamic PMU event.
+    pub fn event(&self, name: &str) -> Result<DynamicPmu> {
+        let Some(terms) = self.events.get(name) else {
+            let error = format!("PMU `{}` has no event `{}`", self.name, name);
+            return Err(Error::new(ErrorKind::NotFound, error));
+        };
+        self.encode(terms)
+    }
+
+    /// Encodes a term list into dynamic PMU event.
+    ///
+    /// The term list is a comma-separated list of terms, same as the one
+    /// used by `perf` in `<pmu>/<terms>/` event syntax:
+    /// - `<format>=<value>` sets the bits of the format field to the value,
+    ///   the value can be decimal or hexadecimal (with `0x` prefix).
+    /// - `<format>` is a shorthand for `<format>=1`.
+    /// - `<event>` expands the event alias.
+    /// - `config=<value>` (also `config1`, `config2` and `config3`) sets the
+    ///   raw config word if the PMU has no format with the same name.
+    ///
+    /// Later terms override the bits set by earlier terms.
+    pub fn encode(&self, terms: &str) -> Result<DynamicPmu> {
+        let mut ev = DynamicPmu {
+            ty: self.ty,
+            config: 0,
+            config1: 0,
+            config2: 0,
+            config3: 0,
+        };
+        self.encode_into(&mut ev, terms, true)?;
+        Ok(ev)
+    }
+
+    fn encode_into(&self, ev: &mut DynamicPmu, terms: &str, expand: bool) -> Result<()> {
+        for term in terms.split(',').map(str::trim).filter(|it| !it.is_empty()) {
+            let (key, value) = match term.split_once('=') {
+                Some((k, v)) => (k.trim(), Some(v.trim())),
+                None => (term, None),
+            };
+
+            if let Some(format) = self.formats.get(key) {
+                let value = match value {
+                    Some(v) => parse_value(v)?,
+                    None => 1,
+                };
+                format.apply(ev, value)?;
+                continue;
+            }
+
+            match (key, value) {
+                ("config", Some(v)) => ev.config = parse_value(v)?,
+                ("config1", Some(v)) => ev.config1 = parse_value(v)?,
+                ("config2", Some(v)) => ev.config2 = parse_value(v)?,
+                ("config3", Some(v)) => ev.config3 = parse_value(v)?,
+                (alias, None) if expand && self.events.contains_key(alias) => {
+                    // Aliases never refer to other aliases, so we only expand once.
+                    self.encode_into(ev, &self.events[alias], false)?;
+                }
+                _ => {
+                    let error = format!("Unknown term `{}` for PMU `{}`", term, self.name);
+                    return Err(Error::new(ErrorKind::InvalidInput, error));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Config word of [`DynamicPmu`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConfigField {
+    /// [`DynamicPmu::config`].
+    Config,
+    /// [`DynamicPmu::config1`].
+    Config1,
+    /// [`DynamicPmu::config2`].
+    Config2,
+    /// [`DynamicPmu::config3`].
+    Config3,
+}
+
+/// PMU format field.
+///
+/// Describes the bits occupied by a term, e.g. `config:0-7,32-35` means the
+/// lower 4 bits of the value are placed in bits 32-35 of `config` after the
+/// lowest 8 bits placed in bits 0-7.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Format {
+    /// Which config word the field belongs to.
+    pub field: ConfigField,
+
+    /// Bit ranges of the field, from the least significant part of the value.
+    pub bits: Vec<RangeInclusive<u8>>,
+}
+
+impl Format {
+    /// Total width of the field in bits.
+    pub fn width(&self) -> u32 {
+        self.bits
+            .iter()
+            .map(|it| (it.end() - it.start()) as u32 + 1)
+            .sum()
+    }
+
+    /// Encodes the value into bits of the config word.
+    ///
+    /// Returns [`ErrorKind::InvalidInput`] if the value is too wide for this field.
+    pub fn encode(&self, value: u64) -> Result<u64> {
+        let width = self.width();
+        if width < 64 && value >> width > 0 {
+            let error = format!("Value {:#x} exceeds the {}-bit format field", value, width);
+            return Err(Error::new(ErrorKind::InvalidInput, error));
+        }
+
+        let mut bits = 0;
+        let mut rest = value;
+        for range in &self.bits {
+            let len = (range.end() - range.start()) as u32 + 1;
+            bits |= (rest & mask(len)) << range.start();
+            rest = rest.checked_shr(len).unwrap_or(0);
+        }
+        Ok(bits)
+    }
+
+    /// Sets the bits of the field in the dynamic PMU event to the value.
+    pub fn apply(&self, ev: &mut DynamicPmu, value: u64) -> Result<()> {
+        let bits = self.encode(value)?;
+        let clear = self.bits.iter().fold(0, |acc, it| {
+            acc | mask(*it.end() as u32 - *it.start() as u32 + 1) << it.start()
+        });
+        let word = match self.field {
+            ConfigField::Config => &mut ev.config,
+            ConfigField::Config1 => &mut ev.config1,
+            ConfigField::Config2 => &mut ev.config2,
+            ConfigField::Config3 => &mut ev.config3,
+        };
+        *word = (*word & !clear) | bits;
+        Ok(())
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((field, ranges)) = s.trim().split_once(':') else {
+            return Err(invalid_data(format!("Missing config word in `{}`", s)));
+        };
+        let field = match field {
+            "config" => ConfigField::Config,
+            "config1" => ConfigField::Config1,
+            "config2" => ConfigField::Config2,
+            "config3" => ConfigField::Config3,
+            _ => return Err(invalid_data(format!("Unknown config word `{}`", field))),
+        };
+
+        let mut bits = vec![];
+        for range in ranges.split(',') {
+            let (lo, hi) = range.split_once('-').unwrap_or((range, range));
+            let parse = |it: &str| match it.trim().parse::<u8>() {
+                Ok(bit) if bit < 64 => Ok(bit),
+                _ => Err(invalid_data(format!("Invalid bit range `{}`", range))),
+            };
+            let (lo, hi) = (parse(lo)?, parse(hi)?);
+            if lo > hi {
+                return Err(invalid_data(format!("Invalid bit range `{}`", range)));
+            }
+            bits.push(lo..=hi);
+        }
+
+        Ok(Self { field, bits })
+    }
+}
+
+const fn mask(len: u32) -> u64 {
+    match len {
+        64.. => u64::MAX,
+        n => (1 << n) - 1,
+    }
+}
+
+fn parse_value(s: &str) -> Result<u64> {
+    let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    result.map_err(|_| {
+        let error = format!("Invalid term value `{}`", s);
+        Error::new(ErrorKind::InvalidInput, error)
+    })
+}
+
+fn dir(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains('/') {
+        let error = format!("Invalid PMU name: `{}`", name);
+        return Err(Error::new(ErrorKind::InvalidInput, error));
+    }
+    let path = Path::new(DEVICES_PATH).join(name);
+    if !path.exists() {
+        let error = format!("PMU `{}` not found in {}", name, DEVICES_PATH);
+        return Err(Error::new(ErrorKind::NotFound, error));
+    }
+    Ok(path)
+}
+
+fn name_of(path: &Path) -> Result<String> {
+    path.file_name()
+        .and_then(|it| it.to_str())
+        .map(|it| it.to_string())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid PMU directory name"))
+}
+
+fn read_type(path: &Path, name: &str) -> Result<u32> {
+    read_trimmed(&path.join("type"))?
+        .parse()
+        .map_err(|_| invalid_data(format!("Invalid type of PMU `{}`", name)))
+}
+
+fn read_formats(path: &Path, name: &str) -> Result<BTreeMap<String, Format>> {
+    let mut formats = BTreeMap::new();
+    for (term, spec) in read_entries(&path.join("format"))? {
+        let format = spec.parse().map_err(|e: Error| {
+            invalid_data(format!(
+                "Invalid format `{}` of PMU `{}`: {}",
+                term, name, e
+            ))
+        })?;
+        formats.insert(term, format);
+    }
+    Ok(formats)
+}
+
+fn invalid_data(error: String) -> Error {
+    Error::new(ErrorKind::InvalidData, error)
+}
+
+fn read_trimmed(path: &Path) -> Result<String> {
+    let content = fs::read_to_string(path)?;
+    Ok(content.trim().to_string())
+}
+
+// Reads all files in the directory as (file name, trimmed content) pairs,
+// returns an empty map if the directory does not exist.
+fn read_entries(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut entries = BTreeMap::new();
+    let dir = match read_dir(dir) {
+        Ok(it) => it,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(entries),
+        Err(e) => return Err(e),
+    };
+    for entry in dir {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|it| it.to_str()) else {
+            continue;
+        };
+        // Some files are write-only or not readable by unprivileged users.
+        let Ok(content) = read_trimmed(&path) else {
+            continue;
+        };
+        entries.insert(name.to_string(), content);
+    }
+    Ok(entries)
+}
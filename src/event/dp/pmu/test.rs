@@ -0,0 +1,104 @@
+use std::collections::BTreeMap;
+
+use super::{ConfigField, Format, Pmu};
+
+fn pmu() -> Pmu {
+    let formats = [
+        ("event", "config:0-7"),
+        ("umask", "config:8-15"),
+        ("edge", "config:18"),
+        ("cmask", "config:24-31"),
+        ("ldlat", "config1:0-15"),
+        ("split", "config2:0-3,8-11"),
+    ];
+    let events = [("cycles", "event=0x3c"), ("mem", "event=0xd0,umask=0x81")];
+
+    Pmu {
+        name: "test".to_string(),
+        ty: 42,
+        formats: formats
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.parse().unwrap()))
+            .collect(),
+        events: events
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        caps: BTreeMap::new(),
+    }
+}
+
+#[test]
+fn test_parse_format() {
+    let format: Format = "config:0-7,32-35".parse().unwrap();
+    assert_eq!(format.field, ConfigField::Config);
+    assert_eq!(format.bits, vec![0..=7, 32..=35]);
+    assert_eq!(format.width(), 12);
+
+    let format: Format = "config1:63\n".parse().unwrap();
+    assert_eq!(format.field, ConfigField::Config1);
+    assert_eq!(format.bits, vec![63..=63]);
+
+    assert!("config:7-0".parse::<Format>().is_err());
+    assert!("config:0-64".parse::<Format>().is_err());
+    assert!("config4:0-7".parse::<Format>().is_err());
+    assert!("0-7".parse::<Format>().is_err());
+}
+
+#[test]
+fn test_encode_format() {
+    let format: Format = "config:0-7,32-35".parse().unwrap();
+    assert_eq!(format.encode(0xabc).unwrap(), 0xa_0000_00bc);
+    assert!(format.encode(0x1000).is_err());
+
+    let format: Format = "config:0-63".parse().unwrap();
+    assert_eq!(format.encode(u64::MAX).unwrap(), u64::MAX);
+}
+
+#[test]
+fn test_encode_terms() {
+    let pmu = pmu();
+
+    let ev = pmu.encode("event=0xd1,umask=0x20,cmask=1").unwrap();
+    assert_eq!(ev.ty, 42);
+    assert_eq!(ev.config, 0x0100_20d1);
+
+    let ev = pmu.encode("mem, ldlat=3, edge").unwrap();
+    assert_eq!(ev.config, 0x4_81d0);
+    assert_eq!(ev.config1, 3);
+
+    let ev = pmu.encode("split=0xab").unwrap();
+    assert_eq!(ev.config2, 0xa0b);
+
+    let ev = pmu.encode("config=0x1234,config3=5").unwrap();
+    assert_eq!(ev.config, 0x1234);
+    assert_eq!(ev.config3, 5);
+
+    // Later terms override earlier ones.
+    let ev = pmu.event("cycles").unwrap();
+    assert_eq!(ev.config, 0x3c);
+    let ev = pmu.encode("cycles,event=0x3d").unwrap();
+    assert_eq!(ev.config, 0x3d);
+
+    assert!(pmu.encode("event=0x100").is_err());
+    assert!(pmu.encode("unknown=1").is_err());
+    assert!(pmu.encode("event=?").is_err());
+    assert!(pmu.event("unknown").is_err());
+}
+
+#[test]
+fn test_all() {
+    Pmu::all().unwrap();
+}
+
+#[test]
+fn test_formats_of() {
+    for pmu in Pmu::all().unwrap() {
+        let lite = Pmu::formats_of(&pmu.name).unwrap();
+        assert_eq!(lite.ty, pmu.ty);
+        assert_eq!(lite.formats, pmu.formats);
+        assert!(lite.events.is_empty());
+    }
+    assert!(Pmu::formats_of("").is_err());
+    assert!(Pmu::formats_of("no-such-pmu").is_err());
+}
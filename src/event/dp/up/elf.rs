@@ -0,0 +1,293 @@
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+const SHT_SYMTAB: u32 = 2;
+const SHT_NOTE: u32 = 7;
+const SHT_DYNSYM: u32 = 11;
+const SHN_UNDEF: u16 = 0;
+const PT_LOAD: u32 = 1;
+
+/// Minimal ELF reader for resolving uprobe locations.
+///
+/// Both ELF32 and ELF64 in either byte order are supported,
+/// only the parts needed by uprobes are parsed. The whole file is read into
+/// memory, and offsets read from the file are checked before use.
+pub(super) struct Elf {
+    data: Vec<u8>,
+    is_64: bool,
+    is_le: bool,
+    sections: Vec<Section>,
+    segments: Vec<Segment>,
+}
+
+pub(super) struct Section {
+    pub name: Vec<u8>,
+    pub ty: u32,
+    pub addr: u64,
+    pub offset: u64,
+    pub size: u64,
+    pub link: u32,
+}
+
+struct Segment {
+    ty: u32,
+    offset: u64,
+    vaddr: u64,
+    filesz: u64,
+}
+
+impl Elf {
+    pub fn open(path: &Path) -> Result<Self> {
+        Self::parse(fs::read(path)?)
+    }
+
+    fn parse(data: Vec<u8>) -> Result<Self> {
+        if data.len() < 52 || data[..4] != *b"\x7fELF" {
+            return Err(invalid("Not an ELF file"));
+        }
+        let is_64 = match data[4] {
+            1 => false,
+            2 => true,
+            _ => return Err(invalid("Invalid ELF class")),
+        };
+        let is_le = match data[5] {
+            1 => true,
+            2 => false,
+            _ => return Err(invalid("Invalid ELF data encoding")),
+        };
+
+        let mut elf = Self {
+            data,
+            is_64,
+            is_le,
+            sections: vec![],
+            segments: vec![],
+        };
+
+        let (phoff, shoff, at) = match is_64 {
+            true => (elf.u64(32)?, elf.u64(40)?, 54),
+            false => (elf.u32(28)? as _, elf.u32(32)? as _, 42),
+        };
+        let phentsize = elf.u16(at)? as u64;
+        let phnum = elf.u16(at + 2)? as u64;
+        let shentsize = elf.u16(at + 4)? as u64;
+        let shnum = elf.u16(at + 6)? as u64;
+        let shstrndx = elf.u16(at + 8)? as usize;
+
+        for i in 0..phnum {
+            let at = elf.entry(phoff, i, phentsize, if is_64 { 40 } else { 20 })?;
+            let segment = match is_64 {
+                true => Segment {
+                    ty: elf.u32(at)?,
+                    offset: elf.u64(at + 8)?,
+                    vaddr: elf.u64(at + 16)?,
+                    filesz: elf.u64(at + 32)?,
+                },
+                false => Segment {
+                    ty: elf.u32(at)?,
+                    offset: elf.u32(at + 4)? as _,
+                    vaddr: elf.u32(at + 8)? as _,
+                    filesz: elf.u32(at + 16)? as _,
+                },
+            };
+            elf.segments.push(segment);
+        }
+
+        let mut names = vec![];
+        for i in 0..shnum {
+            let at = elf.entry(shoff, i, shentsize, if is_64 { 44 } else { 28 })?;
+            let section = match is_64 {
+                true => Section {
+                    name: vec![],
+                    ty: elf.u32(at + 4)?,
+                    addr: elf.u64(at + 16)?,
+                    offset: elf.u64(at + 24)?,
+                    size: elf.u64(at + 32)?,
+                    link: elf.u32(at + 40)?,
+                },
+                false => Section {
+                    name: vec![],
+                    ty: elf.u32(at + 4)?,
+                    addr: elf.u32(at + 12)? as _,
+                    offset: elf.u32(at + 16)? as _,
+                    size: elf.u32(at + 20)? as _,
+                    link: elf.u32(at + 24)?,
+                },
+            };
+            names.push(elf.u32(at)?);
+            elf.sections.push(section);
+        }
+
+        if let Some(shstrtab) = elf.sections.get(shstrndx) {
+            let shstrtab = elf.section_data(shstrtab)?;
+            let names = names
+                .into_iter()
+                .map(|it| str_at(shstrtab, it as _).map(<[u8]>::to_vec))
+                .collect::<Result<Vec<_>>>()?;
+            for (section, name) in elf.sections.iter_mut().zip(names) {
+                section.name = name;
+            }
+        }
+
+        Ok(elf)
+    }
+
+    pub fn is_64(&self) -> bool {
+        self.is_64
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|it| it.name == name.as_bytes())
+    }
+
+    pub fn section_data(&self, section: &Section) -> Result<&[u8]> {
+        self.bytes(section.offset, section.size)
+    }
+
+    /// Returns the note sections with the given name.
+    pub fn notes<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Section> {
+        self.sections
+            .iter()
+            .filter(move |it| it.ty == SHT_NOTE && it.name == name.as_bytes())
+    }
+
+    /// Returns the virtual address of a defined symbol.
+    ///
+    /// `.symtab` is preferred since it also has the local symbols,
+    /// `.dynsym` is used for stripped binaries.
+    pub fn symbol(&self, name: &str) -> Result<Option<u64>> {
+        for ty in [SHT_SYMTAB, SHT_DYNSYM] {
+            for symtab in self.sections.iter().filter(|it| it.ty == ty) {
+                if let Some(addr) = self.find_symbol(symtab, name)? {
+                    return Ok(Some(addr));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    fn find_symbol(&self, symtab: &Section, name: &str) -> Result<Option<u64>> {
+        let strtab = self
+            .sections
+            .get(symtab.link as usize)
+            .ok_or_else(|| invalid("Invalid symbol string table"))?;
+        let strtab = self.section_data(strtab)?;
+
+        let entsize = match self.is_64 {
+            true => 24,
+            false => 16,
+        };
+        for i in 0..symtab.size / entsize {
+            let at = self.entry(symtab.offset, i, entsize, entsize)?;
+            let (sym_name, value, shndx) = match self.is_64 {
+                true => (self.u32(at)?, self.u64(at + 8)?, self.u16(at + 6)?),
+                false => (self.u32(at)?, self.u32(at + 4)? as _, self.u16(at + 14)?),
+            };
+            if shndx == SHN_UNDEF || value == 0 {
+                continue;
+            }
+            if str_at(strtab, sym_name as _)? == name.as_bytes() {
+                return Ok(Some(value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Translates a virtual address to the file offset through the loadable segments.
+    ///
+    /// Uprobes are placed by file offset, so this works for both fixed-address
+    /// executables and position-independent executables or shared libraries.
+    pub fn vaddr_to_offset(&self, vaddr: u64) -> Option<u64> {
+        self.segments
+            .iter()
+            .filter(|it| it.ty == PT_LOAD)
+            .find(|it| {
+                let end = it.vaddr.saturating_add(it.filesz);
+                (it.vaddr..end).contains(&vaddr)
+            })
+            .and_then(|it| (vaddr - it.vaddr).checked_add(it.offset))
+    }
+
+    // Returns the offset of the `i`th `size`-byte entry in the table at `base`,
+    // with at least `len` bytes of the entry in the file, so reading the fields
+    // within `len` bytes from the offset never overflows.
+    fn entry(&self, base: u64, i: u64, size: u64, len: u64) -> Result<u64> {
+        let at = i
+            .checked_mul(size)
+            .and_then(|it| it.checked_add(base))
+            .ok_or_else(|| invalid("Invalid ELF table offset"))?;
+        self.bytes(at, len)?;
+        Ok(at)
+    }
+
+    pub fn bytes(&self, at: u64, len: u64) -> Result<&[u8]> {
+        at.checked_add(len)
+            .and_then(|end| self.data.get(at as usize..end as usize))
+            .ok_or_else(|| invalid("Truncated ELF file"))
+    }
+
+    fn u16(&self, at: u64) -> Result<u16> {
+        let bytes = self.bytes(at, 2)?.try_into().unwrap();
+        Ok(match self.is_le {
+            true => u16::from_le_bytes(bytes),
+            false => u16::from_be_bytes(bytes),
+        })
+    }
+
+    pub fn u32(&self, at: u64) -> Result<u32> {
+        let bytes = self.bytes(at, 4)?.try_into().unwrap();
+        Ok(match self.is_le {
+            true => u32::from_le_bytes(bytes),
+            false => u32::from_be_bytes(bytes),
+        })
+    }
+
+    pub fn u64(&self, at: u64) -> Result<u64> {
+        let bytes = self.bytes(at, 8)?.try_into().unwrap();
+        Ok(match self.is_le {
+            true => u64::from_le_bytes(bytes),
+            false => u64::from_be_bytes(bytes),
+        })
+    }
+
+    /// Reads an address-sized word.
+    pub fn addr(&self, at: u64) -> Result<u64> {
+        match self.is_64 {
+            true => self.u64(at),
+            false => self.u32(at).map(|it| it as _),
+        }
+    }
+}
+
+/// Resolves the file offset of a symbol, which can be used as the uprobe offset.
+pub(super) fn symbol_offset(path: &Path, symbol: &str) -> Result<u64> {
+    let elf = Elf::open(path)?;
+    let vaddr = elf.symbol(symbol)?.ok_or_else(|| {
+        let error = format!("Symbol `{}` not found in {}", symbol, path.display());
+        Error::new(ErrorKind::NotFound, error)
+    })?;
+    elf.vaddr_to_offset(vaddr).ok_or_else(|| {
+        let error = format!(
+            "Symbol `{}` (0x{:x}) is not in any loadable segment of {}",
+            symbol,
+            vaddr,
+            path.display()
+        );
+        invalid(error)
+    })
+}
+
+pub(super) fn str_at(strtab: &[u8], at: usize) -> Result<&[u8]> {
+    strtab
+        .get(at..)
+        .and_then(|it| it.split(|&b| b == 0).next())
+        .ok_or_else(|| invalid("Invalid string table offset"))
+}
+
+pub(super) fn invalid<E>(error: E) -> Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    Error::new(ErrorKind::InvalidData, error)
+}
@@ -1,14 +1,20 @@
+mod elf;
 #[cfg(test)]
 mod test;
+mod usdt;
 
-use std::ffi::CStr;
-use std::io::Result;
+use std::ffi::{CStr, CString, OsStr};
+use std::io::{ErrorKind, Result};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::sync::Arc;
 
-use super::{get_retprobe_bit, get_type, DynamicPmu, Error};
+pub use usdt::*;
+
+use super::{with_keep_alive, DynamicPmu, Error, Pmu};
 use crate::event::Event;
 
-const TYPE_PATH: &str = "/sys/bus/event_source/devices/uprobe/type";
-const RETPROBE_PATH: &str = "/sys/bus/event_source/devices/uprobe/format/retprobe";
+const PMU_NAME: &str = "uprobe";
 
 /// User probe event
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -20,15 +26,32 @@ pub struct Uprobe {
 }
 
 impl Uprobe {
+    /// Creates the probe at the entry of `symbol` in the ELF file at `path`.
+    ///
+    /// The symbol is looked up in `.symtab` and `.dynsym`, and its address is
+    /// translated to the file offset through the program headers, so this works
+    /// for executables (including PIE) and shared libraries.
+    ///
+    /// The whole file is read into memory while looking up the symbol.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use perf_event_open::event::dp::Uprobe;
+    ///
+    /// # let Ok(_) = std::fs::metadata("/lib/x86_64-linux-gnu/libc.so.6") else {
+    /// #     return;
+    /// # };
+    /// let ev = Uprobe::from_symbol(c"/lib/x86_64-linux-gnu/libc.so.6", "malloc").unwrap();
+    /// println!("malloc is at offset 0x{:x}", ev.offset);
+    /// ```
+    pub fn from_symbol(path: &'static CStr, symbol: &str) -> Result<Self> {
+        let offset = elf::symbol_offset(to_path(path), symbol)?;
+        Ok(Self { path, offset })
+    }
+
     pub fn try_into_dp(self) -> Result<DynamicPmu> {
-        let ev = DynamicPmu {
-            ty: get_type(TYPE_PATH)?,
-            config: 0,
-            config1: self.path.as_ptr() as _,
-            config2: self.offset,
-            config3: 0,
-        };
-        Ok(ev)
+        to_dp(false, self.path, self.offset, None)
     }
 }
 
@@ -58,15 +81,16 @@ pub struct Uretprobe {
 }
 
 impl Uretprobe {
+    /// Creates the return probe at the entry of `symbol` in the ELF file at `path`.
+    ///
+    /// See [`Uprobe::from_symbol`] for details.
+    pub fn from_symbol(path: &'static CStr, symbol: &str) -> Result<Self> {
+        let offset = elf::symbol_offset(to_path(path), symbol)?;
+        Ok(Self { path, offset })
+    }
+
     pub fn try_into_dp(self) -> Result<DynamicPmu> {
-        let ev = DynamicPmu {
-            ty: get_type(TYPE_PATH)?,
-            config: 1 << get_retprobe_bit(RETPROBE_PATH)?,
-            config1: self.path.as_ptr() as _,
-            config2: self.offset,
-            config3: 0,
-        };
-        Ok(ev)
+        to_dp(true, self.path, self.offset, None)
     }
 }
 
@@ -85,3 +109,251 @@ impl TryFrom<Uretprobe> for Event {
         value.try_into_dp()?.try_into()
     }
 }
+
+/// User probe event with owned path.
+///
+/// Unlike [`Uprobe`], the path can be chosen at runtime, it will be kept alive
+/// within the [`Event`] and the [`Counter`][crate::count::Counter] opened with it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedUprobe {
+    /// Path to an executable or a library.
+    pub path: CString,
+    /// Where the probe is inserted.
+    pub offset: u64,
+}
+
+impl OwnedUprobe {
+    /// Creates the probe at the entry of `symbol` in the ELF file at `path`.
+    ///
+    /// See [`Uprobe::from_symbol`] for details.
+    pub fn from_symbol(path: impl Into<CString>, symbol: &str) -> Result<Self> {
+        let path = path.into();
+        let offset = elf::symbol_offset(to_path(&path), symbol)?;
+        Ok(Self { path, offset })
+    }
+}
+
+impl TryFrom<&OwnedUprobe> for Event {
+    type Error = Error;
+
+    fn try_from(value: &OwnedUprobe) -> Result<Self> {
+        let path = Arc::new(value.path.clone());
+        with_keep_alive(to_dp(false, &path, value.offset, None)?, path)
+    }
+}
+
+impl TryFrom<OwnedUprobe> for Event {
+    type Error = Error;
+
+    fn try_from(value: OwnedUprobe) -> Result<Self> {
+        (&value).try_into()
+    }
+}
+
+/// User return probe event with owned path.
+///
+/// Unlike [`Uretprobe`], the path can be chosen at runtime, it will be kept alive
+/// within the [`Event`] and the [`Counter`][crate::count::Counter] opened with it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OwnedUretprobe {
+    /// Path to an executable or a library.
+    pub path: CString,
+    /// Where the probe is inserted.
+    pub offset: u64,
+}
+
+impl OwnedUretprobe {
+    /// Creates the return probe at the entry of `symbol` in the ELF file at `path`.
+    ///
+    /// See [`Uretprobe::from_symbol`] for details.
+    pub fn from_symbol(path: impl Into<CString>, symbol: &str) -> Result<Self> {
+        let path = path.into();
+        let offset = elf::symbol_offset(to_path(&path), symbol)?;
+        Ok(Self { path, offset })
+    }
+}
+
+impl TryFrom<&OwnedUretprobe> for Event {
+    type Error = Error;
+
+    fn try_from(value: &OwnedUretprobe) -> Result<Self> {
+        let path = Arc::new(value.path.clone());
+        with_keep_alive(to_dp(true, &path, value.offset, None)?, path)
+    }
+}
+
+impl TryFrom<OwnedUretprobe> for Event {
+    type Error = Error;
+
+    fn try_from(value: OwnedUretprobe) -> Result<Self> {
+        (&value).try_into()
+    }
+}
+
+/// User probe with a reference counter (semaphore).
+///
+/// The kernel increases the reference counter at [`ref_ctr_offset`][Self::ref_ctr_offset] of the probed
+/// file while the probe is attached, which is how USDT probes guarded by semaphores
+/// know they are being traced.
+///
+/// This is created by the `with_ref_ctr` method of the probes, e.g., [`Uprobe::with_ref_ctr`].
+///
+/// Since `linux-4.20`: <https://github.com/torvalds/linux/commit/a6ca88b241d5e929e6e60b12ad8cd288f0ffa256>
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WithRefCtr<P> {
+    /// The probe.
+    pub probe: P,
+    /// File offset of the reference counter.
+    pub ref_ctr_offset: u64,
+}
+
+macro_rules! with_ref_ctr {
+    ($($ty:ident),+) => {
+        $(
+            impl $ty {
+                /// Attaches the probe with a reference counter at `ref_ctr_offset` of the file.
+                ///
+                /// See [`WithRefCtr`] for details.
+                pub fn with_ref_ctr(self, ref_ctr_offset: u64) -> WithRefCtr<Self> {
+                    WithRefCtr {
+                        probe: self,
+                        ref_ctr_offset,
+                    }
+                }
+            }
+        )+
+    };
+}
+
+with_ref_ctr!(Uprobe, Uretprobe, OwnedUprobe, OwnedUretprobe);
+
+impl WithRefCtr<Uprobe> {
+    /// Encodes the probe into dynamic PMU event, with the reference counter
+    /// offset in the `ref_ctr_offset` format field of the uprobe PMU.
+    ///
+    /// Returns [`ErrorKind::Unsupported`] if the PMU has no such field.
+    pub fn try_into_dp(self) -> Result<DynamicPmu> {
+        let Self {
+            probe,
+            ref_ctr_offset,
+        } = self;
+        to_dp(false, probe.path, probe.offset, Some(ref_ctr_offset))
+    }
+}
+
+impl TryFrom<WithRefCtr<Uprobe>> for DynamicPmu {
+    type Error = Error;
+
+    fn try_from(value: WithRefCtr<Uprobe>) -> Result<Self> {
+        value.try_into_dp()
+    }
+}
+
+impl TryFrom<WithRefCtr<Uprobe>> for Event {
+    type Error = Error;
+
+    fn try_from(value: WithRefCtr<Uprobe>) -> Result<Self> {
+        value.try_into_dp()?.try_into()
+    }
+}
+
+impl WithRefCtr<Uretprobe> {
+    /// Encodes the return probe into dynamic PMU event.
+    ///
+    /// See [`WithRefCtr<Uprobe>::try_into_dp`] for details.
+    pub fn try_into_dp(self) -> Result<DynamicPmu> {
+        let Self {
+            probe,
+            ref_ctr_offset,
+        } = self;
+        to_dp(true, probe.path, probe.offset, Some(ref_ctr_offset))
+    }
+}
+
+impl TryFrom<WithRefCtr<Uretprobe>> for DynamicPmu {
+    type Error = Error;
+
+    fn try_from(value: WithRefCtr<Uretprobe>) -> Result<Self> {
+        value.try_into_dp()
+    }
+}
+
+impl TryFrom<WithRefCtr<Uretprobe>> for Event {
+    type Error = Error;
+
+    fn try_from(value: WithRefCtr<Uretprobe>) -> Result<Self> {
+        value.try_into_dp()?.try_into()
+    }
+}
+
+impl TryFrom<&WithRefCtr<OwnedUprobe>> for Event {
+    type Error = Error;
+
+    fn try_from(value: &WithRefCtr<OwnedUprobe>) -> Result<Self> {
+        let path = Arc::new(value.probe.path.clone());
+        let dp = to_dp(false, &path, value.probe.offset, Some(value.ref_ctr_offset))?;
+        with_keep_alive(dp, path)
+    }
+}
+
+impl TryFrom<WithRefCtr<OwnedUprobe>> for Event {
+    type Error = Error;
+
+    fn try_from(value: WithRefCtr<OwnedUprobe>) -> Result<Self> {
+        (&value).try_into()
+    }
+}
+
+impl TryFrom<&WithRefCtr<OwnedUretprobe>> for Event {
+    type Error = Error;
+
+    fn try_from(value: &WithRefCtr<OwnedUretprobe>) -> Result<Self> {
+        let path = Arc::new(value.probe.path.clone());
+        let dp = to_dp(true, &path, value.probe.offset, Some(value.ref_ctr_offset))?;
+        with_keep_alive(dp, path)
+    }
+}
+
+impl TryFrom<WithRefCtr<OwnedUretprobe>> for Event {
+    type Error = Error;
+
+    fn try_from(value: WithRefCtr<OwnedUretprobe>) -> Result<Self> {
+        (&value).try_into()
+    }
+}
+
+fn to_dp(
+    retprobe: bool,
+    path: &CStr,
+    offset: u64,
+    ref_ctr_offset: Option<u64>,
+) -> Result<DynamicPmu> {
+    let pmu = Pmu::formats_of(PMU_NAME)?;
+    let mut config = match retprobe {
+        true => pmu.format("retprobe")?.encode(1)?,
+        false => 0,
+    };
+    if let Some(ref_ctr_offset) = ref_ctr_offset {
+        let Ok(format) = pmu.format("ref_ctr_offset") else {
+            let error =
+                "Reference counter offset is not supported by the uprobe PMU (since Linux 4.20)";
+            return Err(Error::new(ErrorKind::Unsupported, error));
+        };
+        config |= format.encode(ref_ctr_offset)?;
+    }
+    let ev = DynamicPmu {
+        ty: pmu.ty,
+        config,
+        config1: path.as_ptr() as _,
+        config2: offset,
+        config3: 0,
+    };
+    Ok(ev)
+}
+
+fn to_path(path: &CStr) -> &Path {
+    Path::new(OsStr::from_bytes(path.to_bytes()))
+}
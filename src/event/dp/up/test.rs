@@ -1,5 +1,13 @@
-use super::{Uprobe, Uretprobe};
-use crate::event::dp::DynamicPmu;
+use std::ffi::CString;
+use std::fs;
+use std::io::ErrorKind;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+
+use super::{OwnedUprobe, OwnedUretprobe, Uprobe, Uretprobe, Usdt, UsdtArg, UsdtArgLoc, UsdtValue};
+use crate::config::RegsMask;
+use crate::event::dp::{DynamicPmu, Pmu};
+use crate::event::Event;
 
 #[test]
 fn test_from_uprobe() {
@@ -18,3 +26,276 @@ fn test_from_uretprobe() {
     };
     DynamicPmu::try_from(ev).unwrap();
 }
+
+#[test]
+fn test_from_uprobe_ref_ctr() {
+    let ev = Uprobe {
+        path: c"",
+        offset: 0,
+    }
+    .with_ref_ctr(0x1234);
+    let Ok(format) = Pmu::from_name("uprobe")
+        .unwrap()
+        .format("ref_ctr_offset")
+        .cloned()
+    else {
+        DynamicPmu::try_from(ev).unwrap_err();
+        return;
+    };
+    let ev = DynamicPmu::try_from(ev).unwrap();
+    assert_eq!(ev.config, format.encode(0x1234).unwrap());
+}
+
+#[test]
+fn test_from_owned_uprobe() {
+    let ev = OwnedUprobe {
+        path: c"/bin/true".into(),
+        offset: 0,
+    };
+    let Event(event_cfg) = Event::try_from(&ev).unwrap();
+    let path = event_cfg.keep_alive.unwrap();
+    assert_eq!(event_cfg.config1, path.as_ptr() as u64);
+    assert_eq!(path.as_c_str(), ev.path.as_c_str());
+}
+
+#[test]
+fn test_from_owned_uretprobe() {
+    let ev = OwnedUretprobe {
+        path: c"/bin/true".into(),
+        offset: 0,
+    };
+    let Event(event_cfg) = Event::try_from(ev).unwrap();
+    let path = event_cfg.keep_alive.unwrap();
+    assert_eq!(event_cfg.config1, path.as_ptr() as u64);
+}
+
+#[no_mangle]
+#[inline(never)]
+extern "C" fn perf_event_open_test_uprobe_target(n: u64) -> u64 {
+    std::hint::black_box(n).wrapping_mul(31) ^ 7
+}
+
+// The code at the resolved file offset should be the same as the code in memory.
+fn assert_code_eq(path: &Path, offset: u64, func: *const u8) {
+    let file = fs::read(path).unwrap();
+    let in_file = &file[offset as usize..][..16];
+    let in_mem = unsafe { std::slice::from_raw_parts(func, 16) };
+    assert_eq!(in_file, in_mem);
+}
+
+#[test]
+fn test_from_symbol_exe() {
+    let exe = std::env::current_exe().unwrap();
+    let path = CString::new(exe.as_os_str().as_bytes()).unwrap();
+    let ev = OwnedUprobe::from_symbol(path, "perf_event_open_test_uprobe_target").unwrap();
+    let func = perf_event_open_test_uprobe_target as *const u8;
+    assert_code_eq(&exe, ev.offset, func);
+}
+
+#[test]
+fn test_from_symbol_lib() {
+    let maps = fs::read_to_string("/proc/self/maps").unwrap();
+    let Some(libc) = maps
+        .lines()
+        .filter_map(|it| it.split_whitespace().nth(5))
+        .find(|it| it.contains("/libc.so") || it.contains("/libc-"))
+    else {
+        return;
+    };
+    let path = CString::new(libc).unwrap();
+    let ev = OwnedUretprobe::from_symbol(path, "malloc").unwrap();
+    assert_code_eq(Path::new(libc), ev.offset, libc::malloc as *const u8);
+}
+
+#[test]
+fn test_malformed_elf() {
+    let path = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+    let mut elf = [0_u8; 224];
+    elf[..6].copy_from_slice(b"\x7fELF\x02\x01");
+    let open = |elf: &[u8]| {
+        fs::write(&path, elf).unwrap();
+        let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        (
+            OwnedUprobe::from_symbol(path.clone(), "main"),
+            Usdt::all(path),
+        )
+    };
+
+    // Program headers at the end of the address space.
+    elf[32..40].copy_from_slice(&(u64::MAX - 8).to_le_bytes());
+    elf[54..56].copy_from_slice(&56_u16.to_le_bytes());
+    elf[56..58].copy_from_slice(&2_u16.to_le_bytes());
+    let (probe, _) = open(&elf);
+    assert_eq!(probe.unwrap_err().kind(), ErrorKind::InvalidData);
+
+    // Note section at the end of the address space.
+    elf[32..40].fill(0);
+    elf[56..58].fill(0);
+    elf[40..48].copy_from_slice(&64_u64.to_le_bytes());
+    elf[58..60].copy_from_slice(&64_u16.to_le_bytes());
+    elf[60..62].copy_from_slice(&2_u16.to_le_bytes());
+    elf[62..64].copy_from_slice(&1_u16.to_le_bytes());
+    let section = |at: usize, name: u32, ty: u32, offset: u64, size: u64| {
+        let mut it = [0_u8; 64];
+        it[..4].copy_from_slice(&name.to_le_bytes());
+        it[4..8].copy_from_slice(&ty.to_le_bytes());
+        it[24..32].copy_from_slice(&offset.to_le_bytes());
+        it[32..40].copy_from_slice(&size.to_le_bytes());
+        (at, it)
+    };
+    for (at, it) in [
+        section(64, 1, 7, u64::MAX - 4, 16),
+        section(128, 0, 3, 192, 32),
+    ] {
+        elf[at..at + 64].copy_from_slice(&it);
+    }
+    elf[193..206].copy_from_slice(b".note.stapsdt");
+    let (_, usdt) = open(&elf);
+    assert_eq!(usdt.unwrap_err().kind(), ErrorKind::InvalidData);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_from_symbol_not_found() {
+    let exe = std::env::current_exe().unwrap();
+    let path = CString::new(exe.as_os_str().as_bytes()).unwrap();
+    let err = OwnedUprobe::from_symbol(path, "perf_event_open_no_such_symbol").unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+}
+
+#[test]
+fn test_parse_usdt_arg() {
+    let arg = UsdtArg::parse("-4@%edi");
+    assert_eq!(arg.size, 4);
+    assert!(arg.signed);
+    assert_eq!(arg.loc, UsdtArgLoc::Reg("edi".to_string()));
+
+    let arg = UsdtArg::parse("8@-16(%rbp)");
+    assert!(!arg.signed);
+    let loc = UsdtArgLoc::Mem {
+        base: "rbp".to_string(),
+        offset: -16,
+    };
+    assert_eq!(arg.loc, loc);
+
+    assert_eq!(UsdtArg::parse("4@$0x10").loc, UsdtArgLoc::Const(16));
+    assert_eq!(UsdtArg::parse("-1@$-1").loc, UsdtArgLoc::Const(-1));
+    assert_eq!(
+        UsdtArg::parse("8@x1").loc,
+        UsdtArgLoc::Reg("x1".to_string())
+    );
+    let loc = UsdtArgLoc::Mem {
+        base: "sp".to_string(),
+        offset: 16,
+    };
+    assert_eq!(UsdtArg::parse("4@[sp, 16]").loc, loc);
+
+    let spec = "8@8(%rax,%rdx,8)";
+    let loc = UsdtArgLoc::Other("8(%rax,%rdx,8)".to_string());
+    assert_eq!(UsdtArg::parse(spec).loc, loc);
+}
+
+#[test]
+fn test_decode_usdt_arg() {
+    let arg = UsdtArg::parse("-2@$-2");
+    let value = arg.decode(&[], RegsMask(0));
+    assert_eq!(value, Some(UsdtValue::Value(-2_i64 as u64)));
+
+    let arg = UsdtArg::parse("4@$0x100000001");
+    assert_eq!(arg.decode(&[], RegsMask(0)), Some(UsdtValue::Value(1)));
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_decode_usdt_arg_regs() {
+    // ax, di, bp
+    let mask = RegsMask(1 << 0 | 1 << 5 | 1 << 6);
+    let regs = [1, u32::MAX as u64, 0x1000];
+
+    let arg = UsdtArg::parse("-4@%edi");
+    let value = arg.decode(&regs, mask);
+    assert_eq!(value, Some(UsdtValue::Value(-1_i64 as u64)));
+
+    let arg = UsdtArg::parse("8@-8(%rbp)");
+    assert_eq!(arg.decode(&regs, mask), Some(UsdtValue::Addr(0x1000 - 8)));
+
+    // Register not sampled.
+    assert_eq!(UsdtArg::parse("8@%rsi").decode(&regs, mask), None);
+}
+
+// Same as `STAP_PROBE2(perf_event_open, test, a, b)` in `<sys/sdt.h>`.
+#[cfg(target_arch = "x86_64")]
+#[no_mangle]
+#[inline(never)]
+extern "C" fn perf_event_open_test_usdt_target(a: u64, b: i32) {
+    unsafe {
+        std::arch::asm!(
+            "990: nop",
+            ".pushsection .note.stapsdt, \"\", \"note\"",
+            ".balign 4",
+            ".4byte 992f-991f, 994f-993f, 3",
+            "991: .asciz \"stapsdt\"",
+            "992: .balign 4",
+            "993: .8byte 990b",
+            ".8byte _.stapsdt.base",
+            ".8byte 0",
+            ".asciz \"perf_event_open\"",
+            ".asciz \"test\"",
+            ".asciz \"8@{a} -4@{b:e}\"",
+            "994: .balign 4",
+            ".popsection",
+            ".ifndef _.stapsdt.base",
+            ".pushsection .stapsdt.base, \"aG\", \"progbits\", .stapsdt.base, comdat",
+            ".weak _.stapsdt.base",
+            ".hidden _.stapsdt.base",
+            "_.stapsdt.base: .space 1",
+            ".size _.stapsdt.base, 1",
+            ".popsection",
+            ".endif",
+            a = in(reg) a,
+            b = in(reg) b,
+            options(att_syntax, nostack),
+        );
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_usdt_from_name() {
+    std::hint::black_box(perf_event_open_test_usdt_target as extern "C" fn(u64, i32));
+
+    let exe = std::env::current_exe().unwrap();
+    let path = CString::new(exe.as_os_str().as_bytes()).unwrap();
+    let usdt = Usdt::from_name(path, "perf_event_open", "test").unwrap();
+    assert_eq!(usdt.semaphore, None);
+    assert_eq!(usdt.args.len(), 2);
+    assert_eq!((usdt.args[0].size, usdt.args[0].signed), (8, false));
+    assert_eq!((usdt.args[1].size, usdt.args[1].signed), (4, true));
+    assert!(matches!(usdt.args[0].loc, UsdtArgLoc::Reg(_)));
+    assert_eq!(usdt.regs_mask().0.count_ones(), 2);
+
+    // The probe location should be the `nop` instruction.
+    let file = fs::read(&exe).unwrap();
+    assert_eq!(file[usdt.offset as usize], 0x90);
+
+    Event::try_from(&usdt).unwrap();
+}
+
+#[cfg(target_arch = "x86_64")]
+#[test]
+fn test_usdt_count() {
+    use crate::config::{Cpu, Opts, Proc};
+    use crate::count::Counter;
+
+    let exe = std::env::current_exe().unwrap();
+    let path = CString::new(exe.as_os_str().as_bytes()).unwrap();
+    let usdt = Usdt::from_name(path, "perf_event_open", "test").unwrap();
+    let counter = Counter::new(&usdt, (Proc::CURRENT, Cpu::ALL), Opts::default()).unwrap();
+    counter.enable().unwrap();
+    for i in 0..3 {
+        perf_event_open_test_usdt_target(i, -1);
+    }
+    counter.disable().unwrap();
+    assert_eq!(counter.stat().unwrap().count, 3);
+}
@@ -0,0 +1,426 @@
+use std::ffi::CString;
+use std::io::{Error, ErrorKind, Result};
+
+use super::elf::{self, Elf};
+use super::{to_path, OwnedUprobe};
+use crate::config::RegsMask;
+use crate::event::Event;
+
+const NOTE_NAME: &str = ".note.stapsdt";
+const NOTE_OWNER: &[u8] = b"stapsdt";
+const NT_STAPSDT: u32 = 3;
+const BASE_SECTION: &str = ".stapsdt.base";
+
+/// USDT (SystemTap SDT) probe.
+///
+/// USDT probes are static tracepoints compiled into user space programs
+/// (e.g., with `DTRACE_PROBE` in `<sys/sdt.h>`), described by the
+/// `.note.stapsdt` notes of the ELF file.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use perf_event_open::config::{Cpu, Opts, Proc, SampleOn};
+/// use perf_event_open::count::Counter;
+/// use perf_event_open::event::dp::Usdt;
+/// use perf_event_open::sample::record::Record;
+///
+/// let usdt = Usdt::from_name(c"/usr/bin/python3", "python", "function__entry").unwrap();
+///
+/// let mut opts = Opts::default();
+/// opts.sample_on = SampleOn::Count(1);
+/// opts.sample_format.user_regs = Some(usdt.regs_mask());
+///
+/// let counter = Counter::new(&usdt, (Proc::ALL, Cpu(0)), &opts).unwrap();
+/// let sampler = counter.sampler(5).unwrap();
+/// counter.enable().unwrap();
+///
+/// for it in sampler.iter() {
+///     if let (_, Record::Sample(sample)) = it {
+///         let (regs, _) = sample.user_regs.as_ref().unwrap();
+///         let args: Vec<_> = usdt.args.iter().map(|it| it.decode(regs, usdt.regs_mask())).collect();
+///         println!("{:?}", args);
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Usdt {
+    /// Provider name (e.g., `libc` or `python`).
+    pub provider: String,
+    /// Probe name (e.g., `setjmp` or `function__entry`).
+    pub name: String,
+    /// Path to the executable or library that contains the probe.
+    pub path: CString,
+    /// File offset of the probe location.
+    pub offset: u64,
+    /// File offset of the semaphore guarding the probe.
+    ///
+    /// The probe only fires if the semaphore is non-zero, the kernel will
+    /// increase it when the probe is attached.
+    pub semaphore: Option<u64>,
+    /// Probe arguments.
+    pub args: Vec<UsdtArg>,
+}
+
+impl Usdt {
+    /// Returns all USDT probes in the ELF file at `path`.
+    ///
+    /// The whole file is read into memory while parsing the notes.
+    pub fn all(path: impl Into<CString>) -> Result<Vec<Self>> {
+        let path = path.into();
+        let elf = Elf::open(to_path(&path))?;
+        // Prelink may move the binary, the note records the link-time address
+        // of `.stapsdt.base` to adjust the probe address.
+        let base = elf.section(BASE_SECTION).map(|it| it.addr);
+
+        let mut probes = vec![];
+        for notes in elf.notes(NOTE_NAME) {
+            let mut at = notes.offset;
+            let end = notes.offset.checked_add(notes.size);
+            let end = end.ok_or_else(|| elf::invalid("Invalid note section size"))?;
+            while end.saturating_sub(at) >= 12 {
+                let namesz = elf.u32(at)? as u64;
+                let descsz = elf.u32(at + 4)? as u64;
+                let ty = elf.u32(at + 8)?;
+                let name_at = at + 12;
+                let desc_at = name_at.checked_add(namesz.next_multiple_of(4));
+                let next = desc_at.and_then(|it| it.checked_add(descsz.next_multiple_of(4)));
+                let (Some(desc_at), Some(next)) = (desc_at, next) else {
+                    return Err(elf::invalid("Invalid USDT note size"));
+                };
+                at = next;
+
+                let name = elf.bytes(name_at, namesz)?;
+                if ty != NT_STAPSDT || name.strip_suffix(b"\0") != Some(NOTE_OWNER) {
+                    continue;
+                }
+                let desc = elf.bytes(desc_at, descsz)?;
+                probes.push(Self::from_note(&elf, &path, base, desc_at, desc)?);
+            }
+        }
+        Ok(probes)
+    }
+
+    /// Returns the USDT probe `provider:name` in the ELF file at `path`.
+    ///
+    /// If there are multiple locations for the same probe (e.g., the probe
+    /// is in an inlined function), the first one is returned.
+    pub fn from_name(path: impl Into<CString>, provider: &str, name: &str) -> Result<Self> {
+        let path = path.into();
+        Self::all(path.clone())?
+            .into_iter()
+            .find(|it| it.provider == provider && it.name == name)
+            .ok_or_else(|| {
+                let error = format!(
+                    "USDT probe `{}:{}` not found in {}",
+                    provider,
+                    name,
+                    path.to_string_lossy()
+                );
+                Error::new(ErrorKind::NotFound, error)
+            })
+    }
+
+    // Note description:
+    // struct {
+    //     addr_t pc;
+    //     addr_t base; # link-time address of `.stapsdt.base`
+    //     addr_t semaphore;
+    //     char provider[]; # null-terminated
+    //     char name[];
+    //     char args[];
+    // }
+    fn from_note(
+        elf: &Elf,
+        path: &CString,
+        base: Option<u64>,
+        at: u64,
+        desc: &[u8],
+    ) -> Result<Self> {
+        let word = if elf.is_64() { 8 } else { 4 };
+        let mut pc = elf.addr(at)?;
+        let note_base = elf.addr(at + word)?;
+        let semaphore = elf.addr(at + word * 2)?;
+        if let (Some(base), true) = (base, note_base != 0) {
+            pc = pc.wrapping_add(base).wrapping_sub(note_base);
+        }
+
+        let mut strs = desc
+            .get(word as usize * 3..)
+            .ok_or_else(|| elf::invalid("Truncated USDT note"))?
+            .split(|&b| b == 0)
+            .map(|it| String::from_utf8_lossy(it).into_owned());
+        let provider = strs.next().unwrap_or_default();
+        let name = strs.next().unwrap_or_default();
+        let args = strs.next().unwrap_or_default();
+
+        let offset = elf.vaddr_to_offset(pc).ok_or_else(|| {
+            let error = format!(
+                "USDT probe `{}:{}` is not in any loadable segment",
+                provider, name
+            );
+            elf::invalid(error)
+        })?;
+        let semaphore = match semaphore {
+            0 => None,
+            addr => Some(elf.vaddr_to_offset(addr).ok_or_else(|| {
+                let error = format!(
+                    "Semaphore of USDT probe `{}:{}` is not in any loadable segment",
+                    provider, name
+                );
+                elf::invalid(error)
+            })?),
+        };
+        let args = args.split_whitespace().map(UsdtArg::parse).collect();
+
+        Ok(Self {
+            provider,
+            name,
+            path: path.clone(),
+            offset,
+            semaphore,
+            args,
+        })
+    }
+
+    /// Returns the user registers mask needed to [decode][UsdtArg::decode] all arguments.
+    ///
+    /// Use this as [`SampleFormat::user_regs`][crate::config::SampleFormat::user_regs].
+    pub fn regs_mask(&self) -> RegsMask {
+        let mask = self
+            .args
+            .iter()
+            .filter_map(|it| match &it.loc {
+                UsdtArgLoc::Reg(reg) | UsdtArgLoc::Mem { base: reg, .. } => reg_index(reg),
+                _ => None,
+            })
+            .fold(0, |acc, (idx, _)| acc | 1 << idx);
+        RegsMask(mask)
+    }
+
+    /// Returns the uprobe at the probe location, without the semaphore.
+    ///
+    /// Use [`OwnedUprobe::with_ref_ctr`] with [`semaphore`][Self::semaphore] to attach the semaphore.
+    pub fn to_uprobe(&self) -> OwnedUprobe {
+        OwnedUprobe {
+            path: self.path.clone(),
+            offset: self.offset,
+        }
+    }
+}
+
+impl TryFrom<&Usdt> for Event {
+    type Error = Error;
+
+    fn try_from(value: &Usdt) -> Result<Self> {
+        let uprobe = value.to_uprobe();
+        match value.semaphore {
+            Some(semaphore) => uprobe.with_ref_ctr(semaphore).try_into(),
+            None => uprobe.try_into(),
+        }
+    }
+}
+
+impl TryFrom<Usdt> for Event {
+    type Error = Error;
+
+    fn try_from(value: Usdt) -> Result<Self> {
+        (&value).try_into()
+    }
+}
+
+/// USDT probe argument.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UsdtArg {
+    /// Argument size in bytes.
+    pub size: u8,
+    /// Whether the argument is signed.
+    pub signed: bool,
+    /// Where the argument is.
+    pub loc: UsdtArgLoc,
+}
+
+/// Location of USDT probe argument.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UsdtArgLoc {
+    /// Constant value.
+    Const(i64),
+    /// Value in register (e.g., `rdi` or `x0`).
+    Reg(String),
+    /// Value in memory at register + offset.
+    Mem { base: String, offset: i64 },
+    /// Argument spec that can't be understood (e.g., with index register).
+    Other(String),
+}
+
+/// Decoded USDT probe argument.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UsdtValue {
+    /// Argument value, sign-extended if the argument is signed.
+    Value(u64),
+    /// The argument is in memory at this address in the traced process,
+    /// it can be read from the sampled user stack or the process memory.
+    Addr(u64),
+}
+
+impl UsdtArg {
+    /// Parses the argument spec in the note, e.g., `-4@%edi`, `8@-8(%rbp)` or `4@[sp, 16]`.
+    pub fn parse(spec: &str) -> Self {
+        let (size, loc) = match spec.split_once('@') {
+            Some((size, loc)) => match size.parse::<i8>() {
+                Ok(size) => (size, loc),
+                Err(_) => (8, spec),
+            },
+            None => (8, spec),
+        };
+        Self {
+            size: size.unsigned_abs(),
+            signed: size < 0,
+            loc: parse_loc(loc).unwrap_or_else(|| UsdtArgLoc::Other(loc.to_string())),
+        }
+    }
+
+    /// Decodes the argument from [user registers][crate::sample::record::sample::Sample::user_regs].
+    ///
+    /// `mask` is the [`SampleFormat::user_regs`][crate::config::SampleFormat::user_regs]
+    /// used to sample the registers. Returns `None` if the needed register is not sampled,
+    /// or the argument location is not supported on this architecture.
+    pub fn decode(&self, regs: &[u64], mask: RegsMask) -> Option<UsdtValue> {
+        let reg = |name: &str| {
+            let (idx, shift) = reg_index(name)?;
+            if mask.0 & 1 << idx == 0 {
+                return None;
+            }
+            // Registers are dumped in the order of set bits.
+            let pos = (mask.0 & ((1 << idx) - 1)).count_ones() as usize;
+            regs.get(pos).map(|it| it >> shift)
+        };
+        match &self.loc {
+            UsdtArgLoc::Const(value) => Some(UsdtValue::Value(self.extend(*value as u64))),
+            UsdtArgLoc::Reg(name) => reg(name).map(|it| UsdtValue::Value(self.extend(it))),
+            UsdtArgLoc::Mem { base, offset } => {
+                reg(base).map(|it| UsdtValue::Addr(it.wrapping_add(*offset as u64)))
+            }
+            UsdtArgLoc::Other(_) => None,
+        }
+    }
+
+    /// Truncates the value to the argument size, sign-extends it if signed.
+    pub fn extend(&self, value: u64) -> u64 {
+        let bits = self.size as u32 * 8;
+        if bits == 0 || bits >= 64 {
+            return value;
+        }
+        let value = value & ((1 << bits) - 1);
+        match self.signed && value >> (bits - 1) != 0 {
+            true => value | !((1 << bits) - 1),
+            false => value,
+        }
+    }
+}
+
+// x86: `$imm`, `%reg`, `(%reg)`, `disp(%reg)`
+// arm64: `imm`, `reg`, `[reg]`, `[reg, disp]`
+fn parse_loc(loc: &str) -> Option<UsdtArgLoc> {
+    if let Some(imm) = loc.strip_prefix('$') {
+        return parse_int(imm).map(UsdtArgLoc::Const);
+    }
+    if let Some(reg) = loc.strip_prefix('%') {
+        return is_reg(reg).then(|| UsdtArgLoc::Reg(reg.to_string()));
+    }
+    if let Some((disp, rest)) = loc.split_once("(%") {
+        let base = rest.strip_suffix(')').filter(|it| is_reg(it))?;
+        let offset = match disp {
+            "" => 0,
+            disp => parse_int(disp)?,
+        };
+        return Some(UsdtArgLoc::Mem {
+            base: base.to_string(),
+            offset,
+        });
+    }
+    if let Some(mem) = loc.strip_prefix('[').and_then(|it| it.strip_suffix(']')) {
+        let (base, offset) = match mem.split_once(',') {
+            Some((base, disp)) => (base.trim(), parse_int(disp.trim())?),
+            None => (mem.trim(), 0),
+        };
+        return is_reg(base).then(|| UsdtArgLoc::Mem {
+            base: base.to_string(),
+            offset,
+        });
+    }
+    if let Some(imm) = parse_int(loc) {
+        return Some(UsdtArgLoc::Const(imm));
+    }
+    is_reg(loc).then(|| UsdtArgLoc::Reg(loc.to_string()))
+}
+
+fn parse_int(str: &str) -> Option<i64> {
+    let (neg, abs) = match str.strip_prefix('-') {
+        Some(abs) => (true, abs),
+        None => (false, str),
+    };
+    let abs = match abs.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => abs.parse::<u64>().ok()?,
+    } as i64;
+    Some(if neg { abs.wrapping_neg() } else { abs })
+}
+
+fn is_reg(str: &str) -> bool {
+    !str.is_empty() && str.chars().all(|it| it.is_ascii_alphanumeric())
+}
+
+// Returns the index in the perf registers mask and the bit shift of the (sub-)register:
+// https://github.com/torvalds/linux/blob/v6.13/arch/x86/include/uapi/asm/perf_regs.h
+#[cfg(target_arch = "x86_64")]
+fn reg_index(name: &str) -> Option<(u32, u32)> {
+    let idx = match name {
+        "rax" | "eax" | "ax" | "al" | "ah" => 0,
+        "rbx" | "ebx" | "bx" | "bl" | "bh" => 1,
+        "rcx" | "ecx" | "cx" | "cl" | "ch" => 2,
+        "rdx" | "edx" | "dx" | "dl" | "dh" => 3,
+        "rsi" | "esi" | "si" | "sil" => 4,
+        "rdi" | "edi" | "di" | "dil" => 5,
+        "rbp" | "ebp" | "bp" | "bpl" => 6,
+        "rsp" | "esp" | "sp" | "spl" => 7,
+        "rip" | "eip" => 8,
+        _ => {
+            // r8 ~ r15 with optional `d`, `w` or `b` suffix.
+            let num = name.strip_prefix('r')?.trim_end_matches(['d', 'w', 'b']);
+            match num.parse::<u32>().ok()? {
+                n @ 8..=15 => n + 8,
+                _ => return None,
+            }
+        }
+    };
+    let shift = match name {
+        "ah" | "bh" | "ch" | "dh" => 8,
+        _ => 0,
+    };
+    Some((idx, shift))
+}
+
+// https://github.com/torvalds/linux/blob/v6.13/arch/arm64/include/uapi/asm/perf_regs.h
+#[cfg(target_arch = "aarch64")]
+fn reg_index(name: &str) -> Option<(u32, u32)> {
+    let idx = match name {
+        "lr" => 30,
+        "sp" => 31,
+        "pc" => 32,
+        _ => match name.strip_prefix(['x', 'w'])?.parse::<u32>().ok()? {
+            n @ 0..=30 => n,
+            _ => return None,
+        },
+    };
+    Some((idx, 0))
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn reg_index(_: &str) -> Option<(u32, u32)> {
+    None
+}
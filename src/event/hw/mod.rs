@@ -1,6 +1,12 @@
 use super::EventConfig;
 use crate::ffi::bindings as b;
 
+mod probe;
+#[cfg(test)]
+mod test;
+
+pub use probe::*;
+
 /// Generalized hardware CPU events.
 ///
 /// Not all of these are available on all platforms.
@@ -121,6 +127,7 @@ super::try_from!(Hardware, value, {
                 config2: 0,
                 config3: 0,
                 bp_type: 0,
+                keep_alive: None,
             };
 
             return Ok(Self(event_config));
@@ -145,6 +152,7 @@ super::try_from!(Hardware, value, {
         config2: 0,
         config3: 0,
         bp_type: 0,
+        keep_alive: None,
     };
 
     Ok(Self(event_config))
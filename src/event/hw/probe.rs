@@ -0,0 +1,120 @@
+use std::hint::black_box;
+use std::io::Result;
+
+use super::{Hardware, Op, OpResult, Type};
+use crate::config::{Cpu, Opts, Proc};
+use crate::count::Counter;
+
+/// Support status of hardware event on the current CPU.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Support {
+    /// The event can be opened and was scheduled on the PMU.
+    Supported,
+    /// The event can be opened but was never scheduled on the PMU.
+    ///
+    /// This usually means the event is accepted by the kernel but no hardware
+    /// counter can count it, e.g., the counters are occupied by other pinned events
+    /// or the PMU is not (fully) virtualized.
+    NeverScheduled,
+    /// The event is not supported by the CPU or PMU driver.
+    Unsupported,
+    /// The event is supported but we have no permission to use it.
+    ///
+    /// See [permission of `Counter`][Counter#permission].
+    NotPermitted,
+}
+
+impl Hardware {
+    /// Returns all generic hardware events and all combinations of cache events.
+    pub fn all() -> Vec<Self> {
+        let mut events = vec![
+            Self::CpuCycle,
+            Self::BusCycle,
+            Self::RefCpuCycle,
+            Self::CacheMiss,
+            Self::CacheAccess,
+            Self::BranchMiss,
+            Self::BranchInstr,
+            Self::BackendStalledCycle,
+            Self::FrontendStalledCycle,
+            Self::Instr,
+        ];
+        for ty in [
+            Type::L1d,
+            Type::L1i,
+            Type::Ll,
+            Type::Dtlb,
+            Type::Itlb,
+            Type::Bpu,
+            Type::Node,
+        ] {
+            for op in [Op::Read, Op::Write, Op::Prefetch] {
+                for result in [OpResult::Access, OpResult::Miss] {
+                    events.push(Self::Cache(ty, op, result));
+                }
+            }
+        }
+        events
+    }
+}
+
+/// Probes which hardware events are supported on the current CPU.
+///
+/// Every event in [`Hardware::all`] is counted for a short workload on the
+/// current process in user space.
+///
+/// Errors other than unsupported or not permitted (e.g., too many open files)
+/// are returned as is.
+///
+/// # Examples
+///
+/// ```rust
+/// use perf_event_open::event::hw::{probe, Support};
+///
+/// for (event, support) in probe().unwrap() {
+///     if support != Support::Supported {
+///         println!("{:?} is {:?}", event, support);
+///     }
+/// }
+/// ```
+pub fn probe() -> Result<Vec<(Hardware, Support)>> {
+    let mut opts = Opts::default();
+    // Kernel profiling may be disallowed by `perf_event_paranoid`,
+    // which doesn't tell whether the event is supported.
+    opts.exclude.kernel = true;
+    opts.exclude.hv = true;
+    opts.stat_format.time_running = true;
+
+    Hardware::all()
+        .into_iter()
+        .map(|event| {
+            let support = probe_event(&event, &opts)?;
+            Ok((event, support))
+        })
+        .collect()
+}
+
+fn probe_event(event: &Hardware, opts: &Opts) -> Result<Support> {
+    let counter = match Counter::new(event, (Proc::CURRENT, Cpu::ALL), opts) {
+        Ok(counter) => counter,
+        Err(e) => {
+            return match e.raw_os_error() {
+                Some(libc::ENOENT | libc::EOPNOTSUPP | libc::EINVAL | libc::ENODEV) => {
+                    Ok(Support::Unsupported)
+                }
+                Some(libc::EACCES | libc::EPERM) => Ok(Support::NotPermitted),
+                _ => Err(e),
+            };
+        }
+    };
+
+    counter.enable()?;
+    black_box((0..10000_u64).fold(0, |acc, it| black_box(acc ^ it.wrapping_mul(31))));
+    counter.disable()?;
+
+    match counter.stat()?.time_running {
+        Some(0) => Ok(Support::NeverScheduled),
+        _ => Ok(Support::Supported),
+    }
+}
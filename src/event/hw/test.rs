@@ -0,0 +1,17 @@
+use std::collections::HashSet;
+
+use super::{probe, Hardware};
+
+#[test]
+fn test_all() {
+    let all = Hardware::all();
+    assert_eq!(all.len(), 10 + 7 * 3 * 2);
+    assert_eq!(all.iter().collect::<HashSet<_>>().len(), all.len());
+}
+
+#[test]
+fn test_probe() {
+    let matrix = probe().unwrap();
+    let events: Vec<_> = matrix.iter().map(|(event, _)| event.clone()).collect();
+    assert_eq!(events, Hardware::all());
+}
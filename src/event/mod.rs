@@ -2,8 +2,14 @@ pub mod bp;
 pub mod dp;
 pub mod hw;
 pub mod raw;
+pub mod spec;
 pub mod sw;
 pub mod tp;
+#[cfg(feature = "vendor")]
+pub mod vendor;
+
+use std::ffi::CString;
+use std::sync::Arc;
 
 /// Unified event type.
 ///
@@ -19,6 +25,19 @@ pub(super) struct EventConfig {
     pub config2: u64,
     pub config3: u64,
     pub bp_type: u32,
+    // Strings pointed by the config words (e.g. probe symbols),
+    // they must outlive the counter opened with this event.
+    pub keep_alive: Option<Arc<CString>>,
+}
+
+// The owned conversion is covered by the blanket `TryFrom<T> for T` impl,
+// whose error type is `Infallible`, so only the borrowed one is provided.
+impl TryFrom<&Event> for Event {
+    type Error = std::io::Error;
+
+    fn try_from(value: &Event) -> std::result::Result<Self, Self::Error> {
+        Ok(value.clone())
+    }
 }
 
 macro_rules! try_from {
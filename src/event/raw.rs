@@ -25,6 +25,7 @@ super::try_from!(Raw, value, {
         config2: value.config2,
         config3: value.config3,
         bp_type: 0,
+        keep_alive: None,
     };
     Ok(Self(event_config))
 });
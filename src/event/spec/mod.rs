@@ -0,0 +1,577 @@
+use std::io::{Error, ErrorKind, Result};
+use std::str::FromStr;
+
+use super::bp::{Breakpoint, Len, Type as BpType};
+use super::dp::Pmu;
+use super::hw::{Hardware, Op, OpResult, Type as CacheType};
+use super::raw::Raw;
+use super::sw::Software;
+use super::tp::Tracepoint;
+use super::Event;
+use crate::config::{sibling, Opts, Priv, SampleSkid, Target};
+use crate::count::group::CounterGroup;
+use crate::count::Counter;
+
+#[cfg(test)]
+mod test;
+
+/// Event specification in the syntax of the `perf` command line tool.
+///
+/// A specification is either a single event such as `cycles:u`, or a group
+/// of events enclosed in braces such as `{cycles,instructions}:S`, the first
+/// event in braces is the group leader.
+///
+/// Supported event names:
+/// - Generalized hardware events, e.g. `cycles`, `instructions`, `ref-cycles`.
+/// - Hardware cache events, e.g. `L1-dcache-load-misses`, `dTLB-stores`.
+/// - Software events, e.g. `task-clock`, `page-faults`, `cs`.
+/// - Raw events, e.g. `r1a8`.
+/// - Dynamic PMU events, e.g. `cpu/event=0x3c,umask=0/`, see [`Pmu::encode`].
+/// - Tracepoint events, e.g. `sched:sched_switch`.
+/// - Breakpoint events, e.g. `mem:0x1000:rw/4`, the access defaults to `rw`
+///   and the length defaults to 4 bytes.
+///
+/// Supported modifiers (after `:`, or right after the trailing `/` of dynamic PMU events):
+/// - `u`, `k`, `h`: count only in user space, kernel or hypervisor.
+/// - `G`, `H`: count only in guest or host mode.
+/// - `I`: exclude the idle task.
+/// - `p`: sample skid level, can be repeated up to 3 times (see [`SampleSkid`]).
+/// - `P`: the maximum sample skid level supported by the `cpu` PMU.
+/// - `S`: the group leader samples the counts of the whole group.
+/// - `D`: pin the group on PMU ([`Opts::pin_on_pmu`]).
+/// - `e`: the group owns the PMU exclusively ([`Opts::only_group`]).
+///
+/// Modifiers after the group braces apply to all events in the group,
+/// privilege modifiers of an event take precedence over the group ones.
+///
+/// # Examples
+///
+/// ```rust
+/// use perf_event_open::config::SampleSkid;
+/// use perf_event_open::event::spec::Spec;
+///
+/// let specs = Spec::parse_list("cycles:upp,{instructions:k,cache-misses}:S").unwrap();
+/// assert_eq!(specs.len(), 2);
+///
+/// assert_eq!(specs[0].name, "cycles");
+/// assert!(specs[0].opts.exclude.kernel);
+/// assert_eq!(specs[0].opts.sample_skid, SampleSkid::ReqZero);
+///
+/// assert_eq!(specs[1].name, "instructions");
+/// assert!(specs[1].opts.sample_format.stat);
+/// assert_eq!(specs[1].siblings[0].name, "cache-misses");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Spec {
+    /// Event name without modifiers.
+    pub name: String,
+
+    /// Event (or group leader event).
+    pub event: Event,
+
+    /// Event (or group leader event) options.
+    pub opts: Opts,
+
+    /// Sibling events in the group.
+    pub siblings: Vec<SiblingSpec>,
+}
+
+/// Sibling event specification in the group.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SiblingSpec {
+    /// Event name without modifiers.
+    pub name: String,
+
+    /// Sibling event.
+    pub event: Event,
+
+    /// Sibling event options.
+    pub opts: sibling::Opts,
+}
+
+impl Spec {
+    /// Parses comma-separated event specifications.
+    pub fn parse_list(specs: &str) -> Result<Vec<Self>> {
+        split(specs)?.into_iter().map(Self::from_str).collect()
+    }
+
+    /// Opens the event (group) for the target.
+    ///
+    /// A single event will be opened as a group without siblings.
+    pub fn open(&self, target: impl Into<Target>) -> Result<CounterGroup> {
+        let leader = Counter::new(&self.event, target, &self.opts)?;
+        let mut group = CounterGroup::from(leader);
+        for sibling in &self.siblings {
+            group.add(&sibling.event, &sibling.opts)?;
+        }
+        Ok(group)
+    }
+}
+
+impl FromStr for Spec {
+    type Err = Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let spec = spec.trim();
+
+        let Some(group) = spec.strip_prefix('{') else {
+            let (name, event, mods) = parse_event(spec)?;
+            let mut opts = Opts::default();
+            mods.apply(&mut opts.exclude);
+            opts.sample_skid = mods.sample_skid();
+            opts.sample_format.stat = mods.sample_read;
+            opts.pin_on_pmu = mods.pinned;
+            opts.only_group = mods.exclusive;
+            return Ok(Self {
+                name,
+                event,
+                opts,
+                siblings: vec![],
+            });
+        };
+
+        let Some((members, group_mods)) = group.rsplit_once('}') else {
+            return Err(invalid(format!("Unclosed group `{}`", spec)));
+        };
+        let group_mods = match group_mods {
+            "" => Modifiers::default(),
+            mods => match mods.strip_prefix(':') {
+                Some(mods) => mods.parse()?,
+                None => return Err(invalid(format!("Invalid group modifiers `{}`", mods))),
+            },
+        };
+
+        let mut members = split(members)?.into_iter();
+        let Some(leader) = members.next() else {
+            return Err(invalid(format!("Empty group `{}`", spec)));
+        };
+
+        let (name, event, mods) = parse_event(leader)?;
+        let mods = group_mods.merge(&mods);
+        let mut opts = Opts::default();
+        mods.apply(&mut opts.exclude);
+        opts.sample_skid = mods.sample_skid();
+        opts.sample_format.stat = mods.sample_read;
+        opts.stat_format.siblings = mods.sample_read;
+        opts.pin_on_pmu = mods.pinned;
+        opts.only_group = mods.exclusive;
+
+        let siblings = members
+            .map(|member| {
+                let (name, event, mods) = parse_event(member)?;
+                if mods.pinned || mods.exclusive {
+                    let error =
+                        format!("Only group leader can be pinned or exclusive: `{}`", member);
+                    return Err(invalid(error));
+                }
+                let mods = group_mods.merge(&mods);
+                let mut opts = sibling::Opts::default();
+                mods.apply(&mut opts.exclude);
+                opts.sample_skid = mods.sample_skid();
+                Ok(SiblingSpec { name, event, opts })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self {
+            name,
+            event,
+            opts,
+            siblings,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Modifiers {
+    user: bool,
+    kernel: bool,
+    hv: bool,
+    guest: bool,
+    host: bool,
+    non_idle: bool,
+    precise: u8,
+    max_precise: bool,
+    sample_read: bool,
+    pinned: bool,
+    exclusive: bool,
+}
+
+impl FromStr for Modifiers {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut mods = Self::default();
+        for c in s.chars() {
+            match c {
+                'u' => mods.user = true,
+                'k' => mods.kernel = true,
+                'h' => mods.hv = true,
+                'G' => mods.guest = true,
+                'H' => mods.host = true,
+                'I' => mods.non_idle = true,
+                'p' if mods.precise < 3 => mods.precise += 1,
+                'p' => return Err(invalid(format!("Too many precise modifiers in `{}`", s))),
+                'P' => mods.max_precise = true,
+                'S' => mods.sample_read = true,
+                'D' => mods.pinned = true,
+                'e' => mods.exclusive = true,
+                _ => return Err(invalid(format!("Unknown modifier `{}` in `{}`", c, s))),
+            }
+        }
+        Ok(mods)
+    }
+}
+
+impl Modifiers {
+    fn merge(&self, event: &Self) -> Self {
+        let privs = |m: &Self| m.user || m.kernel || m.hv;
+        let base = if privs(event) { event } else { self };
+        Self {
+            user: base.user,
+            kernel: base.kernel,
+            hv: base.hv,
+            guest: self.guest || event.guest,
+            host: self.host || event.host,
+            non_idle: self.non_idle || event.non_idle,
+            precise: self.precise.max(event.precise),
+            max_precise: self.max_precise || event.max_precise,
+            sample_read: self.sample_read || event.sample_read,
+            pinned: self.pinned || event.pinned,
+            exclusive: self.exclusive || event.exclusive,
+        }
+    }
+
+    fn apply(&self, exclude: &mut Priv) {
+        if self.user || self.kernel || self.hv {
+            exclude.user = !self.user;
+            exclude.kernel = !self.kernel;
+            exclude.hv = !self.hv;
+        }
+        // Specifying both means counting in both modes.
+        if self.guest != self.host {
+            exclude.host = self.guest;
+            exclude.guest = self.host;
+        }
+        exclude.idle = self.non_idle;
+    }
+
+    fn sample_skid(&self) -> SampleSkid {
+        let precise = if self.max_precise {
+            // Fall back to arbitrary skid if the PMU does not report its capability,
+            // so the event can be always opened.
+            Pmu::from_name("cpu")
+                .ok()
+                .and_then(|pmu| pmu.caps.get("max_precise")?.parse().ok())
+                .unwrap_or(0)
+                .max(self.precise)
+        } else {
+            self.precise
+        };
+        match precise {
+            0 => SampleSkid::Arbitrary,
+            1 => SampleSkid::Const,
+            2 => SampleSkid::ReqZero,
+            _ => SampleSkid::Zero,
+        }
+    }
+}
+
+/// Splits comma-separated specifications, commas in groups and PMU terms are ignored.
+fn split(specs: &str) -> Result<Vec<&str>> {
+    let mut parts = vec![];
+    let mut start = 0;
+    let mut token = 0;
+    let mut depth = 0;
+    let mut in_terms = false;
+
+    for (i, c) in specs.char_indices() {
+        match c {
+            '{' if !in_terms => {
+                depth += 1;
+                token = i + 1;
+            }
+            '}' if !in_terms => match depth {
+                0 => return Err(invalid(format!("Unbalanced braces in `{}`", specs))),
+                _ => depth -= 1,
+            },
+            '/' if in_terms => in_terms = false,
+            '/' => {
+                // A slash right after a PMU name opens the term list,
+                // while slashes in other events (e.g. `mem:0x1000/4`) do not.
+                in_terms = !specs[token..i].contains([':', '/', '}']);
+            }
+            ',' if !in_terms => {
+                token = i + 1;
+                if depth == 0 {
+                    parts.push(specs[start..i].trim());
+                    start = i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    if depth != 0 || in_terms {
+        return Err(invalid(format!("Unclosed group or terms in `{}`", specs)));
+    }
+    parts.push(specs[start..].trim());
+
+    match parts.iter().any(|it| it.is_empty()) {
+        true => Err(invalid(format!("Empty event in `{}`", specs))),
+        false => Ok(parts),
+    }
+}
+
+fn parse_event(spec: &str) -> Result<(String, Event, Modifiers)> {
+    let spec = spec.trim();
+
+    if let Some(bp) = spec.strip_prefix("mem:") {
+        return parse_breakpoint(spec, bp);
+    }
+
+    // `<pmu>/<terms>/<mods>`
+    if let Some((pmu, rest)) = spec.split_once('/') {
+        let Some((terms, mods)) = rest.split_once('/') else {
+            return Err(invalid(format!("Unclosed terms in `{}`", spec)));
+        };
+        let mods = mods.strip_prefix(':').unwrap_or(mods).parse()?;
+        let event = Pmu::from_name(pmu)?.encode(terms)?.try_into()?;
+        let name = spec[..pmu.len() + terms.len() + 2].to_string();
+        return Ok((name, event, mods));
+    }
+
+    let (name, mods) = spec.split_once(':').unwrap_or((spec, ""));
+    if let Some(event) = parse_named(name)? {
+        return Ok((name.to_string(), event, mods.parse()?));
+    }
+
+    // `r<hex>[:<mods>]`, tracepoint subsystems may also look like this (e.g. `rbd`),
+    // so it is a raw event only if the rest are modifiers, same as perf.
+    if let Some(raw) = parse_raw(name)? {
+        if let Ok(mods) = mods.parse() {
+            return Ok((name.to_string(), raw.try_into()?, mods));
+        }
+    }
+
+    // `<subsystem>:<name>:<mods>`
+    match mods.split_once(':').unwrap_or((mods, "")) {
+        ("", _) => Err(invalid(format!("Unknown event `{}`", spec))),
+        (tp, mods) => {
+            let event = Tracepoint::from_name(name, tp)?.try_into()?;
+            let name = format!("{}:{}", name, tp);
+            Ok((name, event, mods.parse()?))
+        }
+    }
+}
+
+// `mem:<addr>[/<len>][:<access>[/<len>]][:<mods>]`
+fn parse_breakpoint(spec: &str, bp: &str) -> Result<(String, Event, Modifiers)> {
+    let mut parts = bp.split(':');
+    let (addr, mut len) = split_len(parts.next().unwrap_or_default())?;
+    let addr = parse_num(addr)?;
+
+    let mut access = "rw";
+    let mut name_end = "mem:".len() + bp.len();
+    let mut mods = Modifiers::default();
+
+    let rest: Vec<_> = parts.collect();
+    let mods_part = match rest.as_slice() {
+        [] => None,
+        [part] | [part, _] if is_access(part) => {
+            let (acc, acc_len) = split_len(part)?;
+            access = acc;
+            len = len.or(acc_len);
+            rest.get(1)
+        }
+        [part] => Some(part),
+        _ => return Err(invalid(format!("Invalid breakpoint `{}`", spec))),
+    };
+    if let Some(part) = mods_part {
+        mods = part.parse()?;
+        name_end -= part.len() + 1;
+    }
+
+    let len = match len.unwrap_or(4) {
+        1 => Len::_1,
+        2 => Len::_2,
+        3 => Len::_3,
+        4 => Len::_4,
+        5 => Len::_5,
+        6 => Len::_6,
+        7 => Len::_7,
+        8 => Len::_8,
+        len => return Err(invalid(format!("Invalid breakpoint length `{}`", len))),
+    };
+    let ty = match access {
+        "r" => BpType::R(len),
+        "w" => BpType::W(len),
+        "rw" | "wr" => BpType::Rw(len),
+        "x" => BpType::X,
+        _ => return Err(invalid(format!("Invalid breakpoint access `{}`", access))),
+    };
+
+    let event = Breakpoint { ty, addr }.try_into()?;
+    Ok((spec[..name_end].to_string(), event, mods))
+}
+
+fn is_access(part: &str) -> bool {
+    let access = part.split('/').next().unwrap_or_default();
+    !access.is_empty() && access.chars().all(|c| matches!(c, 'r' | 'w' | 'x'))
+}
+
+fn split_len(s: &str) -> Result<(&str, Option<u64>)> {
+    match s.split_once('/') {
+        Some((s, len)) => Ok((s, Some(parse_num(len)?))),
+        None => Ok((s, None)),
+    }
+}
+
+fn parse_num(s: &str) -> Result<u64> {
+    let result = match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => s.parse(),
+    };
+    result.map_err(|_| invalid(format!("Invalid number `{}`", s)))
+}
+
+fn parse_named(name: &str) -> Result<Option<Event>> {
+    let lower = name.to_ascii_lowercase();
+
+    if let Some(hw) = HARDWARE.iter().find(|(it, _)| *it == lower) {
+        return hw.1.clone().try_into().map(Some);
+    }
+    if let Some(sw) = SOFTWARE.iter().find(|(it, _)| *it == lower) {
+        return sw.1.try_into().map(Some);
+    }
+    if let Some(cache) = parse_cache(&lower) {
+        return cache.try_into().map(Some);
+    }
+
+    Ok(None)
+}
+
+// `r<hex>`, the whole name must be hex digits after `r`.
+fn parse_raw(name: &str) -> Result<Option<Raw>> {
+    let Some(config) = name.strip_prefix(['r', 'R']) else {
+        return Ok(None);
+    };
+    if config.is_empty() || !config.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Ok(None);
+    }
+    let raw = Raw {
+        config: u64::from_str_radix(config, 16)
+            .map_err(|_| invalid(format!("Invalid raw event `{}`", name)))?,
+        config1: 0,
+        config2: 0,
+        config3: 0,
+    };
+    Ok(Some(raw))
+}
+
+// `<cache>-<op>[-<result>]` or `<cache>-<result>`
+fn parse_cache(name: &str) -> Option<Hardware> {
+    let (ty, rest) = CACHE_TYPES.iter().find_map(|(prefix, ty)| {
+        let rest = name.strip_prefix(prefix)?.strip_prefix('-')?;
+        Some((*ty, rest))
+    })?;
+
+    let result = |s: &str| CACHE_RESULTS.iter().find(|(it, _)| *it == s).map(|it| it.1);
+    // Some op names contain `-`, so we match them by prefix.
+    let (op, result) = match CACHE_OPS.iter().find_map(|(it, op)| {
+        let rest = rest.strip_prefix(it)?;
+        Some((*op, rest))
+    }) {
+        Some((op, "")) => (op, OpResult::Access),
+        Some((op, rest)) => (op, result(rest.strip_prefix('-')?)?),
+        None => (Op::Read, result(rest)?),
+    };
+
+    Some(Hardware::Cache(ty, op, result))
+}
+
+fn invalid(error: String) -> Error {
+    Error::new(ErrorKind::InvalidInput, error)
+}
+
+// Names are matched case-insensitively, keep them lowercase here.
+
+const HARDWARE: &[(&str, Hardware)] = &[
+    ("cpu-cycles", Hardware::CpuCycle),
+    ("cycles", Hardware::CpuCycle),
+    ("instructions", Hardware::Instr),
+    ("cache-references", Hardware::CacheAccess),
+    ("cache-misses", Hardware::CacheMiss),
+    ("branch-instructions", Hardware::BranchInstr),
+    ("branches", Hardware::BranchInstr),
+    ("branch-misses", Hardware::BranchMiss),
+    ("bus-cycles", Hardware::BusCycle),
+    ("stalled-cycles-frontend", Hardware::FrontendStalledCycle),
+    ("idle-cycles-frontend", Hardware::FrontendStalledCycle),
+    ("stalled-cycles-backend", Hardware::BackendStalledCycle),
+    ("idle-cycles-backend", Hardware::BackendStalledCycle),
+    ("ref-cycles", Hardware::RefCpuCycle),
+];
+
+const SOFTWARE: &[(&str, Software)] = &[
+    ("cpu-clock", Software::CpuClock),
+    ("task-clock", Software::TaskClock),
+    ("page-faults", Software::PageFault),
+    ("faults", Software::PageFault),
+    ("minor-faults", Software::MinorPageFault),
+    ("major-faults", Software::MajorPageFault),
+    ("emulation-faults", Software::EmuFault),
+    ("alignment-faults", Software::AlignFault),
+    ("context-switches", Software::CtxSwitch),
+    ("cs", Software::CtxSwitch),
+    ("cgroup-switches", Software::CgroupSwitch),
+    ("dummy", Software::Dummy),
+    ("bpf-output", Software::BpfOutput),
+    ("cpu-migrations", Software::CpuMigration),
+    ("migrations", Software::CpuMigration),
+];
+
+// Longer prefixes go first since we match by prefix.
+const CACHE_TYPES: &[(&str, CacheType)] = &[
+    ("l1-dcache", CacheType::L1d),
+    ("l1-data", CacheType::L1d),
+    ("l1-d", CacheType::L1d),
+    ("l1d", CacheType::L1d),
+    ("l1-icache", CacheType::L1i),
+    ("l1-instruction", CacheType::L1i),
+    ("l1-i", CacheType::L1i),
+    ("l1i", CacheType::L1i),
+    ("llc", CacheType::Ll),
+    ("l2", CacheType::Ll),
+    ("dtlb", CacheType::Dtlb),
+    ("d-tlb", CacheType::Dtlb),
+    ("data-tlb", CacheType::Dtlb),
+    ("itlb", CacheType::Itlb),
+    ("i-tlb", CacheType::Itlb),
+    ("instruction-tlb", CacheType::Itlb),
+    ("branch", CacheType::Bpu),
+    ("bpu", CacheType::Bpu),
+    ("btb", CacheType::Bpu),
+    ("bpc", CacheType::Bpu),
+    ("node", CacheType::Node),
+];
+
+const CACHE_OPS: &[(&str, Op)] = &[
+    ("loads", Op::Read),
+    ("load", Op::Read),
+    ("read", Op::Read),
+    ("stores", Op::Write),
+    ("store", Op::Write),
+    ("write", Op::Write),
+    ("prefetches", Op::Prefetch),
+    ("prefetch", Op::Prefetch),
+    ("speculative-read", Op::Prefetch),
+    ("speculative-load", Op::Prefetch),
+];
+
+const CACHE_RESULTS: &[(&str, OpResult)] = &[
+    ("refs", OpResult::Access),
+    ("reference", OpResult::Access),
+    ("ops", OpResult::Access),
+    ("access", OpResult::Access),
+    ("misses", OpResult::Miss),
+    ("miss", OpResult::Miss),
+];
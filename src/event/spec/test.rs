@@ -0,0 +1,143 @@
+use super::Spec;
+use crate::config::{Priv, SampleSkid};
+use crate::event::bp::{Breakpoint, Len, Type as BpType};
+use crate::event::hw::{Hardware, Op, OpResult, Type as CacheType};
+use crate::event::raw::Raw;
+use crate::event::sw::Software;
+use crate::event::Event;
+
+fn event(ev: impl TryInto<Event, Error = std::io::Error>) -> Event {
+    ev.try_into().unwrap()
+}
+
+fn parse(spec: &str) -> Spec {
+    spec.parse().unwrap()
+}
+
+#[test]
+fn test_parse_named() {
+    assert_eq!(parse("cycles").event, event(Hardware::CpuCycle));
+    assert_eq!(parse("CPU-Cycles").event, event(Hardware::CpuCycle));
+    assert_eq!(parse("ref-cycles").event, event(Hardware::RefCpuCycle));
+    assert_eq!(parse("branches").event, event(Hardware::BranchInstr));
+    assert_eq!(parse("cs").event, event(Software::CtxSwitch));
+    assert_eq!(parse("task-clock").event, event(Software::TaskClock));
+
+    let cache = |ty, op, result| event(Hardware::Cache(ty, op, result));
+    let l1d_load_miss = cache(CacheType::L1d, Op::Read, OpResult::Miss);
+    assert_eq!(parse("L1-dcache-load-misses").event, l1d_load_miss);
+    assert_eq!(parse("l1d-misses").event, l1d_load_miss);
+    let dtlb_store = cache(CacheType::Dtlb, Op::Write, OpResult::Access);
+    assert_eq!(parse("dTLB-stores").event, dtlb_store);
+    let llc_prefetch = cache(CacheType::Ll, Op::Prefetch, OpResult::Miss);
+    assert_eq!(parse("LLC-speculative-read-misses").event, llc_prefetch);
+
+    let raw = Raw {
+        config: 0x1a8,
+        config1: 0,
+        config2: 0,
+        config3: 0,
+    };
+    assert_eq!(parse("r1a8").event, event(raw.clone()));
+    assert_eq!(parse("r1a8:u").event, event(raw));
+    let raw = Raw {
+        config: 0xbad,
+        config1: 0,
+        config2: 0,
+        config3: 0,
+    };
+    assert_eq!(parse("rbad").event, event(raw));
+    // Tracepoint subsystems that look like raw events.
+    let error = "rbd:rbd_do_request".parse::<Spec>().unwrap_err();
+    assert!(!error.to_string().contains("modifier"), "{}", error);
+    assert!("r1a8g".parse::<Spec>().is_err());
+
+    assert!("cycle".parse::<Spec>().is_err());
+    assert!("L1-dcache-load-hits".parse::<Spec>().is_err());
+}
+
+#[test]
+fn test_parse_breakpoint() {
+    let bp = |ty| event(Breakpoint { ty, addr: 0x1000 });
+
+    let spec = parse("mem:0x1000:rw/4");
+    assert_eq!(spec.name, "mem:0x1000:rw/4");
+    assert_eq!(spec.event, bp(BpType::Rw(Len::_4)));
+
+    assert_eq!(parse("mem:4096").event, bp(BpType::Rw(Len::_4)));
+    assert_eq!(parse("mem:0x1000/8:w").event, bp(BpType::W(Len::_8)));
+    assert_eq!(parse("mem:0x1000:x").event, bp(BpType::X));
+
+    let spec = parse("mem:0x1000:r/2:u");
+    assert_eq!(spec.name, "mem:0x1000:r/2");
+    assert_eq!(spec.event, bp(BpType::R(Len::_2)));
+    assert!(spec.opts.exclude.kernel);
+
+    let spec = parse("mem:0x1000:k");
+    assert_eq!(spec.event, bp(BpType::Rw(Len::_4)));
+    assert!(spec.opts.exclude.user);
+
+    assert!("mem:0x1000/9".parse::<Spec>().is_err());
+    assert!("mem:0x1000:rw:u:k".parse::<Spec>().is_err());
+}
+
+#[test]
+fn test_parse_modifiers() {
+    let spec = parse("cycles:u");
+    let exclude = Priv {
+        kernel: true,
+        hv: true,
+        ..Default::default()
+    };
+    assert_eq!(spec.opts.exclude, exclude);
+
+    let spec = parse("instructions:kpp");
+    assert!(spec.opts.exclude.user && !spec.opts.exclude.kernel);
+    assert_eq!(spec.opts.sample_skid, SampleSkid::ReqZero);
+
+    let spec = parse("cycles:GIDe");
+    assert!(spec.opts.exclude.host && !spec.opts.exclude.guest);
+    assert!(spec.opts.exclude.idle);
+    assert!(spec.opts.pin_on_pmu && spec.opts.only_group);
+
+    let spec = parse("cycles:GH");
+    assert!(!spec.opts.exclude.host && !spec.opts.exclude.guest);
+
+    assert!("cycles:pppp".parse::<Spec>().is_err());
+    assert!("cycles:x".parse::<Spec>().is_err());
+}
+
+#[test]
+fn test_parse_group() {
+    let spec = parse("{cycles,instructions:k}:S");
+    assert_eq!(spec.event, event(Hardware::CpuCycle));
+    assert!(spec.opts.sample_format.stat);
+    assert!(spec.opts.stat_format.siblings);
+    assert_eq!(spec.siblings.len(), 1);
+    assert_eq!(spec.siblings[0].name, "instructions");
+    assert_eq!(spec.siblings[0].event, event(Hardware::Instr));
+    assert!(spec.siblings[0].opts.exclude.user);
+
+    // Privilege modifiers of an event take precedence over the group ones.
+    let spec = parse("{cycles,instructions:k}:up");
+    assert!(spec.opts.exclude.kernel);
+    assert_eq!(spec.opts.sample_skid, SampleSkid::Const);
+    assert!(!spec.siblings[0].opts.exclude.kernel);
+    assert_eq!(spec.siblings[0].opts.sample_skid, SampleSkid::Const);
+
+    assert!("{cycles,instructions:D}".parse::<Spec>().is_err());
+    assert!("{cycles,instructions".parse::<Spec>().is_err());
+    assert!("{}".parse::<Spec>().is_err());
+}
+
+#[test]
+fn test_parse_list() {
+    let specs = Spec::parse_list("cycles:u, {instructions,mem:0x1000/4:w}:S,cs").unwrap();
+    let names: Vec<_> = specs.iter().map(|it| it.name.as_str()).collect();
+    assert_eq!(names, ["cycles", "instructions", "cs"]);
+    assert_eq!(specs[1].siblings[0].name, "mem:0x1000/4:w");
+
+    assert!(Spec::parse_list("cycles,,cs").is_err());
+    assert!(Spec::parse_list("cycles}").is_err());
+    assert!(Spec::parse_list("cpu/event=0x3c").is_err());
+}
@@ -85,6 +85,7 @@ super::try_from!(Software, value, {
         config2: 0,
         config3: 0,
         bp_type: 0,
+        keep_alive: None,
     };
     Ok(Self(event_config))
 });
@@ -0,0 +1,361 @@
+use std::fs;
+use std::io::{self, ErrorKind, Result};
+use std::str::FromStr;
+
+use super::{event_dir, Error};
+
+/// Tracepoint format parsed from tracefs `events/<subsystem>/<name>/format`.
+///
+/// The format describes the layout of [raw sample data][crate::sample::record::sample::Sample::raw]
+/// generated by the tracepoint, which varies between kernel versions.
+///
+/// # Examples
+///
+/// ```rust, no_run
+/// use perf_event_open::config::{Cpu, Opts, Proc, SampleOn};
+/// use perf_event_open::count::Counter;
+/// use perf_event_open::event::tp::{Tracepoint, TracepointFormat};
+/// use perf_event_open::sample::record::Record;
+///
+/// let tp = Tracepoint::from_name("sched", "sched_switch").unwrap();
+/// let format = TracepointFormat::from_name("sched", "sched_switch").unwrap();
+///
+/// let mut opts = Opts::default();
+/// opts.sample_on = SampleOn::Count(1);
+/// opts.sample_format.raw = true;
+///
+/// let counter = Counter::new(tp, (Proc::ALL, Cpu(0)), opts).unwrap();
+/// let sampler = counter.sampler(5).unwrap();
+/// counter.enable().unwrap();
+///
+/// for it in sampler.iter() {
+///     if let (_, Record::Sample(sample)) = it {
+///         let fields = format.decode(sample.raw.as_deref().unwrap()).unwrap();
+///         println!("{:?} -> {:?}", fields.get("prev_comm"), fields.get("next_comm"));
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TracepointFormat {
+    /// Tracepoint name.
+    pub name: String,
+    /// Tracepoint ID.
+    pub id: u64,
+    /// Fields in the raw data, including the `common_*` fields.
+    pub fields: Vec<FieldFormat>,
+}
+
+/// Field layout in the raw data.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FieldFormat {
+    /// Field name.
+    pub name: String,
+    /// C type of the field, e.g. `unsigned long` or `__data_loc char[]`.
+    pub ty: String,
+    /// Offset in the raw data.
+    pub offset: usize,
+    /// Size in the raw data.
+    pub size: usize,
+    /// Whether the value (or array element) is signed.
+    pub signed: bool,
+    /// Field kind.
+    pub kind: FieldKind,
+}
+
+/// Field kind.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FieldKind {
+    /// Single value.
+    Scalar,
+    /// Fixed-size array with length.
+    Array(usize),
+    /// Dynamic array stored after the fixed fields.
+    ///
+    /// The field itself is a `u32` with the data offset (relative to the raw data start)
+    /// in the lower 16 bits and the data length in the higher 16 bits.
+    DataLoc,
+    /// Same as [`FieldKind::DataLoc`] but the offset is relative to the end of the field.
+    RelLoc,
+}
+
+/// Decoded field value.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Value<'a> {
+    /// Signed integer.
+    Signed(i64),
+    /// Unsigned integer (also pointers).
+    Unsigned(u64),
+    /// Character array or dynamic string, truncated at the first NUL.
+    Str(&'a [u8]),
+    /// Signed integer array.
+    SignedArray(Vec<i64>),
+    /// Unsigned integer array.
+    UnsignedArray(Vec<u64>),
+    /// Data that can not be interpreted as the above types.
+    Bytes(&'a [u8]),
+}
+
+impl Value<'_> {
+    /// Returns the value as `i64` if it is an integer.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Signed(v) => Some(*v),
+            Self::Unsigned(v) => Some(*v as _),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as `u64` if it is an integer.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::Signed(v) => Some(*v as _),
+            Self::Unsigned(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as string if it is a valid UTF-8 string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::Str(s) => std::str::from_utf8(s).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Fields decoded from the raw data.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Fields<'a> {
+    format: &'a TracepointFormat,
+    values: Vec<Value<'a>>,
+}
+
+impl<'a> Fields<'a> {
+    /// Returns the value of the field.
+    pub fn get(&self, name: &str) -> Option<&Value<'a>> {
+        let index = self.format.fields.iter().position(|it| it.name == name)?;
+        self.values.get(index)
+    }
+
+    /// Iterates over the field names and values in the format order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Value<'a>)> {
+        let names = self.format.fields.iter().map(|it| it.name.as_str());
+        names.zip(self.values.iter())
+    }
+}
+
+impl TracepointFormat {
+    /// Reads the format of the tracepoint from tracefs.
+    pub fn from_name(subsystem: &str, name: &str) -> std::result::Result<Self, Error> {
+        let path = event_dir(subsystem, name)?.join("format");
+        let format = fs::read_to_string(&path).map_err(|e| match e.kind() {
+            ErrorKind::NotFound => Error::NotFound(format!("{}:{}", subsystem, name)),
+            ErrorKind::PermissionDenied => Error::PermissionDenied(path),
+            _ => Error::Io(e),
+        })?;
+        Ok(format.parse()?)
+    }
+
+    /// Returns the field format.
+    pub fn field(&self, name: &str) -> Option<&FieldFormat> {
+        self.fields.iter().find(|it| it.name == name)
+    }
+
+    /// Decodes the raw data according to the format.
+    pub fn decode<'a>(&'a self, raw: &'a [u8]) -> Result<Fields<'a>> {
+        let values = self
+            .fields
+            .iter()
+            .map(|it| it.decode(raw))
+            .collect::<Result<_>>()?;
+        Ok(Fields {
+            format: self,
+            values,
+        })
+    }
+}
+
+impl FieldFormat {
+    /// Decodes the field value from the raw data.
+    pub fn decode<'a>(&self, raw: &'a [u8]) -> Result<Value<'a>> {
+        let bytes = slice(raw, self.offset, self.size, &self.name)?;
+
+        let value = match self.kind {
+            FieldKind::Scalar => match int(bytes, self.signed) {
+                Some(v) => v,
+                None => Value::Bytes(bytes),
+            },
+            FieldKind::Array(_) if self.is_char() => Value::Str(until_nul(bytes)),
+            FieldKind::Array(len) => {
+                let elem_size = self.size.checked_div(len).unwrap_or(0);
+                match elem_size {
+                    1 | 2 | 4 | 8 => {
+                        let elems = bytes.chunks_exact(elem_size);
+                        match self.signed {
+                            true => Value::SignedArray(elems.map(signed).collect()),
+                            false => Value::UnsignedArray(elems.map(unsigned).collect()),
+                        }
+                    }
+                    _ => Value::Bytes(bytes),
+                }
+            }
+            FieldKind::DataLoc | FieldKind::RelLoc => {
+                let loc = unsigned(&bytes[..4.min(bytes.len())]);
+                let mut offset = (loc & 0xffff) as usize;
+                let len = (loc >> 16) as usize;
+                if self.kind == FieldKind::RelLoc {
+                    offset += self.offset + self.size;
+                }
+                let data = slice(raw, offset, len, &self.name)?;
+                match self.is_char() {
+                    true => Value::Str(until_nul(data)),
+                    false => Value::Bytes(data),
+                }
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn is_char(&self) -> bool {
+        let ty = self.ty.trim_start_matches("__data_loc ");
+        let ty = ty.trim_start_matches("__rel_loc ");
+        let ty = ty.trim_start_matches("const ");
+        ty.starts_with("char")
+    }
+}
+
+impl FromStr for TracepointFormat {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut name = None;
+        let mut id = None;
+        let mut fields = vec![];
+
+        for line in s.lines().map(str::trim) {
+            if let Some(it) = line.strip_prefix("name:") {
+                name = Some(it.trim().to_string());
+            } else if let Some(it) = line.strip_prefix("ID:") {
+                id = Some(it.trim().parse().map_err(|_| invalid_format(line))?);
+            } else if line.starts_with("field:") {
+                fields.push(line.parse()?);
+            } else if line.starts_with("print fmt:") {
+                break;
+            }
+        }
+
+        match (name, id) {
+            (Some(name), Some(id)) => Ok(Self { name, id, fields }),
+            _ => Err(invalid_format("missing name or ID")),
+        }
+    }
+}
+
+// field:unsigned short common_type;	offset:0;	size:2;	signed:0;
+impl FromStr for FieldFormat {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut decl = None;
+        let mut offset = None;
+        let mut size = None;
+        // Old kernels have no `signed`.
+        let mut signed = false;
+
+        for part in s.split(';').map(str::trim).filter(|it| !it.is_empty()) {
+            let Some((key, value)) = part.split_once(':') else {
+                return Err(invalid_format(s));
+            };
+            let num = || value.trim().parse::<usize>().map_err(|_| invalid_format(s));
+            match key.trim() {
+                "field" | "field special" => decl = Some(value.trim()),
+                "offset" => offset = Some(num()?),
+                "size" => size = Some(num()?),
+                "signed" => signed = num()? != 0,
+                _ => {}
+            }
+        }
+
+        let (Some(decl), Some(offset), Some(size)) = (decl, offset, size) else {
+            return Err(invalid_format(s));
+        };
+        let Some((ty, name)) = decl.rsplit_once([' ', '\t']) else {
+            return Err(invalid_format(s));
+        };
+        let ty = ty.trim().to_string();
+
+        let (name, kind) = match name.split_once('[') {
+            // Array length can be a macro name in old kernels, treat it as unknown.
+            Some((name, len)) => {
+                let len = len.trim_end_matches(']').parse().unwrap_or(0);
+                (name, FieldKind::Array(len))
+            }
+            None if ty.starts_with("__data_loc") => (name, FieldKind::DataLoc),
+            None if ty.starts_with("__rel_loc") => (name, FieldKind::RelLoc),
+            None => (name, FieldKind::Scalar),
+        };
+
+        Ok(Self {
+            name: name.to_string(),
+            ty,
+            offset,
+            size,
+            signed,
+            kind,
+        })
+    }
+}
+
+fn slice<'a>(raw: &'a [u8], offset: usize, size: usize, name: &str) -> Result<&'a [u8]> {
+    match offset
+        .checked_add(size)
+        .and_then(|end| raw.get(offset..end))
+    {
+        Some(bytes) => Ok(bytes),
+        None => {
+            let error = format!("Field `{}` is out of raw data bounds", name);
+            Err(io::Error::new(ErrorKind::InvalidData, error))
+        }
+    }
+}
+
+fn int(bytes: &[u8], is_signed: bool) -> Option<Value<'_>> {
+    if !matches!(bytes.len(), 1 | 2 | 4 | 8) {
+        return None;
+    }
+    Some(match is_signed {
+        true => Value::Signed(signed(bytes)),
+        false => Value::Unsigned(unsigned(bytes)),
+    })
+}
+
+fn unsigned(bytes: &[u8]) -> u64 {
+    let mut buf = [0; 8];
+    // Raw data is in native endian.
+    #[cfg(target_endian = "little")]
+    buf[..bytes.len()].copy_from_slice(bytes);
+    #[cfg(target_endian = "big")]
+    buf[8 - bytes.len()..].copy_from_slice(bytes);
+    u64::from_ne_bytes(buf)
+}
+
+fn signed(bytes: &[u8]) -> i64 {
+    let shift = 64 - bytes.len() * 8;
+    ((unsigned(bytes) << shift) as i64) >> shift
+}
+
+fn until_nul(bytes: &[u8]) -> &[u8] {
+    match bytes.iter().position(|&b| b == 0) {
+        Some(end) => &bytes[..end],
+        None => bytes,
+    }
+}
+
+fn invalid_format(s: &str) -> io::Error {
+    let error = format!("Invalid tracepoint format `{}`", s);
+    io::Error::new(ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,231 @@
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+use thiserror::Error;
+
+use super::EventConfig;
+use crate::ffi::bindings as b;
+
+mod format;
+#[cfg(test)]
+mod test;
+
+pub use format::*;
+
+// Tracefs is mounted at `/sys/kernel/tracing` on recent systems,
+// older systems only have it automounted under debugfs.
+const TRACEFS_PATHS: [&str; 2] = ["/sys/kernel/tracing", "/sys/kernel/debug/tracing"];
+
+/// Tracepoint event provided by the kernel tracepoint infrastructure.
+///
+/// # Examples
+///
+/// ```rust
+/// use perf_event_open::event::tp::Tracepoint;
+///
+/// // Tracefs may be not mounted or not accessible without privileges.
+/// let Ok(tp) = Tracepoint::from_name("sched", "sched_switch") else {
+///     return;
+/// };
+/// println!("{}", tp.id);
+///
+/// for entry in Tracepoint::glob("sched:sched_*").unwrap() {
+///     println!("{}:{} {}", entry.subsystem, entry.name, entry.tracepoint.id);
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Tracepoint {
+    /// Tracepoint ID from tracefs `events/*/*/id`.
+    ///
+    /// See also [`Tracepoint::from_name`] to look up the ID by name.
+    pub id: u64,
+}
+
+impl Tracepoint {
+    /// Looks up the tracepoint by subsystem and name in tracefs.
+    pub fn from_name(subsystem: &str, name: &str) -> Result<Self, Error> {
+        let dir = event_dir(subsystem, name)?;
+        read_id(&dir).map_err(|e| match e {
+            Error::NotFound(_) => Error::NotFound(format!("{}:{}", subsystem, name)),
+            e => e,
+        })
+    }
+
+    /// Lists tracepoints matching the `<subsystem>:<name>` glob pattern.
+    ///
+    /// Both parts can contain `*` (matches any characters) and `?` (matches a single character),
+    /// e.g. `sched:*` or `*:sys_enter_*`. The results are sorted by subsystem and name.
+    pub fn glob(pattern: &str) -> Result<Vec<TracepointEntry>, Error> {
+        let Some((subsystem_pat, name_pat)) = pattern.split_once(':') else {
+            let error = format!("Invalid tracepoint pattern `{}`", pattern);
+            return Err(io::Error::new(ErrorKind::InvalidInput, error).into());
+        };
+
+        let events = tracefs()?.join("events");
+        let mut entries = vec![];
+        for subsystem in read_dirs(&events)? {
+            if !glob_match(subsystem_pat, &subsystem) {
+                continue;
+            }
+            for name in read_dirs(&events.join(&subsystem))? {
+                if !glob_match(name_pat, &name) {
+                    continue;
+                }
+                let tracepoint = read_id(&events.join(&subsystem).join(&name))?;
+                entries.push(TracepointEntry {
+                    subsystem: subsystem.clone(),
+                    name,
+                    tracepoint,
+                });
+            }
+        }
+        Ok(entries)
+    }
+}
+
+super::try_from!(Tracepoint, value, {
+    let event_config = EventConfig {
+        ty: b::PERF_TYPE_TRACEPOINT,
+        config: value.id,
+        config1: 0,
+        config2: 0,
+        config3: 0,
+        bp_type: 0,
+        keep_alive: None,
+    };
+    Ok(Self(event_config))
+});
+
+/// Tracepoint listed by [`Tracepoint::glob`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TracepointEntry {
+    /// Tracepoint subsystem.
+    pub subsystem: String,
+    /// Tracepoint name.
+    pub name: String,
+    /// Tracepoint event.
+    pub tracepoint: Tracepoint,
+}
+
+/// Tracepoint lookup errors.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Tracefs is not mounted at any known path.
+    #[error("tracefs is not mounted at {}", TRACEFS_PATHS.join(" or "))]
+    NotMounted,
+    /// The tracepoint does not exist.
+    #[error("tracepoint `{0}` not found")]
+    NotFound(String),
+    /// No permission to access the path, usually requires root or `CAP_SYS_ADMIN`.
+    #[error("permission denied to access `{}`", .0.display())]
+    PermissionDenied(PathBuf),
+    /// Other I/O errors.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl From<Error> for io::Error {
+    fn from(value: Error) -> Self {
+        let kind = match &value {
+            Error::NotMounted | Error::NotFound(_) => ErrorKind::NotFound,
+            Error::PermissionDenied(_) => ErrorKind::PermissionDenied,
+            Error::Io(e) => e.kind(),
+        };
+        match value {
+            Error::Io(e) => e,
+            e => io::Error::new(kind, e),
+        }
+    }
+}
+
+/// Returns the path of mounted tracefs.
+pub fn tracefs() -> Result<PathBuf, Error> {
+    for path in TRACEFS_PATHS {
+        // The mount point exists even if tracefs is not mounted,
+        // so we check the `events` directory instead.
+        let events = Path::new(path).join("events");
+        match fs::metadata(&events) {
+            Ok(meta) if meta.is_dir() => return Ok(path.into()),
+            Ok(_) => continue,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) if e.kind() == ErrorKind::PermissionDenied => {
+                return Err(Error::PermissionDenied(events))
+            }
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(Error::NotMounted)
+}
+
+fn event_dir(subsystem: &str, name: &str) -> Result<PathBuf, Error> {
+    // Reject path components to avoid escaping the events directory.
+    if [subsystem, name]
+        .iter()
+        .any(|it| it.is_empty() || it.contains('/') || *it == "." || *it == "..")
+    {
+        return Err(Error::NotFound(format!("{}:{}", subsystem, name)));
+    }
+    Ok(tracefs()?.join("events").join(subsystem).join(name))
+}
+
+fn read_id(dir: &Path) -> Result<Tracepoint, Error> {
+    let path = dir.join("id");
+    let id = fs::read_to_string(&path).map_err(|e| map_io(e, &path))?;
+    let id = id.trim().parse().map_err(|_| {
+        let error = format!("Invalid tracepoint ID in `{}`", path.display());
+        io::Error::new(ErrorKind::InvalidData, error)
+    })?;
+    Ok(Tracepoint { id })
+}
+
+fn read_dirs(path: &Path) -> Result<Vec<String>, Error> {
+    let mut dirs = vec![];
+    for entry in fs::read_dir(path).map_err(|e| map_io(e, path))? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            dirs.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+fn map_io(e: io::Error, path: &Path) -> Error {
+    match e.kind() {
+        ErrorKind::NotFound => Error::NotFound(path.display().to_string()),
+        ErrorKind::PermissionDenied => Error::PermissionDenied(path.into()),
+        _ => Error::Io(e),
+    }
+}
+
+fn glob_match(pattern: &str, s: &str) -> bool {
+    let (pattern, s) = (pattern.as_bytes(), s.as_bytes());
+    let (mut p, mut i) = (0, 0);
+    // Position of the last `*` and the input position it matched up to.
+    let mut star = None;
+
+    while i < s.len() {
+        match pattern.get(p) {
+            Some(b'*') => {
+                star = Some((p, i));
+                p += 1;
+            }
+            Some(&c) if c == b'?' || c == s[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match star {
+                Some((sp, si)) => {
+                    p = sp + 1;
+                    i = si + 1;
+                    star = Some((sp, si + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == b'*')
+}
@@ -0,0 +1,111 @@
+use super::{glob_match, Error, FieldKind, Tracepoint, TracepointFormat, Value};
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("*", ""));
+    assert!(glob_match("*", "sched_switch"));
+    assert!(glob_match("sched_*", "sched_switch"));
+    assert!(glob_match("*_switch", "sched_switch"));
+    assert!(glob_match("s*d*h", "sched_switch"));
+    assert!(glob_match("sched_s?itch", "sched_switch"));
+    assert!(glob_match("sched_switch", "sched_switch"));
+
+    assert!(!glob_match("sched_", "sched_switch"));
+    assert!(!glob_match("?", ""));
+    assert!(!glob_match("*_wakeup", "sched_switch"));
+    assert!(!glob_match("sched_switch?", "sched_switch"));
+}
+
+#[test]
+fn test_from_name() {
+    match Tracepoint::from_name("sched", "sched_switch") {
+        Ok(_) | Err(Error::NotMounted | Error::PermissionDenied(_)) => {}
+        Err(e) => panic!("{}", e),
+    }
+    match Tracepoint::from_name("..", "sched_switch") {
+        Err(Error::NotMounted | Error::PermissionDenied(_) | Error::NotFound(_)) => {}
+        _ => panic!(),
+    }
+}
+
+const FORMAT: &str = "\
+name: sched_switch
+ID: 316
+format:
+\tfield:unsigned short common_type;\toffset:0;\tsize:2;\tsigned:0;
+\tfield:unsigned char common_flags;\toffset:2;\tsize:1;\tsigned:0;
+\tfield:unsigned char common_preempt_count;\toffset:3;\tsize:1;\tsigned:0;
+\tfield:int common_pid;\toffset:4;\tsize:4;\tsigned:1;
+
+\tfield:char prev_comm[16];\toffset:8;\tsize:16;\tsigned:0;
+\tfield:long prev_state;\toffset:24;\tsize:8;\tsigned:1;
+\tfield:u32 cpus[2];\toffset:32;\tsize:8;\tsigned:0;
+\tfield:__data_loc char[] name;\toffset:40;\tsize:4;\tsigned:0;
+\tfield:__rel_loc char[] rel;\toffset:44;\tsize:4;\tsigned:0;
+
+print fmt: \"prev_comm=%s\", REC->prev_comm
+";
+
+#[test]
+fn test_parse_format() {
+    let format: TracepointFormat = FORMAT.parse().unwrap();
+    assert_eq!(format.name, "sched_switch");
+    assert_eq!(format.id, 316);
+    assert_eq!(format.fields.len(), 9);
+
+    let field = format.field("common_pid").unwrap();
+    assert_eq!(field.ty, "int");
+    assert_eq!((field.offset, field.size, field.signed), (4, 4, true));
+    assert_eq!(field.kind, FieldKind::Scalar);
+
+    let field = format.field("prev_comm").unwrap();
+    assert_eq!(field.ty, "char");
+    assert_eq!(field.kind, FieldKind::Array(16));
+
+    let field = format.field("name").unwrap();
+    assert_eq!(field.ty, "__data_loc char[]");
+    assert_eq!(field.kind, FieldKind::DataLoc);
+    assert_eq!(format.field("rel").unwrap().kind, FieldKind::RelLoc);
+
+    assert!("name: x\nformat:\n".parse::<TracepointFormat>().is_err());
+    assert!("name: x\nID: 1\n\tfield:int a;\toffset:x;"
+        .parse::<TracepointFormat>()
+        .is_err());
+}
+
+#[test]
+fn test_decode() {
+    let format: TracepointFormat = FORMAT.parse().unwrap();
+
+    let mut raw = vec![];
+    raw.extend(316_u16.to_ne_bytes());
+    raw.extend([1, 2]);
+    raw.extend((-1_i32).to_ne_bytes());
+    raw.extend(b"swapper/0\0\0\0\0\0\0\0");
+    raw.extend((-2_i64).to_ne_bytes());
+    raw.extend(7_u32.to_ne_bytes());
+    raw.extend(8_u32.to_ne_bytes());
+    // `name` at offset 48 with 4 bytes.
+    raw.extend((48_u32 | (4 << 16)).to_ne_bytes());
+    // `rel` at offset 52, relative to the field end (48).
+    raw.extend((4_u32 | (3 << 16)).to_ne_bytes());
+    raw.extend(b"foo\0");
+    raw.extend(b"ba\0");
+
+    let fields = format.decode(&raw).unwrap();
+    assert_eq!(fields.get("common_type"), Some(&Value::Unsigned(316)));
+    assert_eq!(
+        fields.get("common_preempt_count"),
+        Some(&Value::Unsigned(2))
+    );
+    assert_eq!(fields.get("common_pid").unwrap().as_i64(), Some(-1));
+    assert_eq!(fields.get("prev_comm").unwrap().as_str(), Some("swapper/0"));
+    assert_eq!(fields.get("prev_state"), Some(&Value::Signed(-2)));
+    assert_eq!(fields.get("cpus"), Some(&Value::UnsignedArray(vec![7, 8])));
+    assert_eq!(fields.get("name").unwrap().as_str(), Some("foo"));
+    assert_eq!(fields.get("rel").unwrap().as_str(), Some("ba"));
+    assert_eq!(fields.get("unknown"), None);
+    assert_eq!(fields.iter().count(), 9);
+
+    assert!(format.decode(&raw[..40]).is_err());
+}
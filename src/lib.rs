@@ -61,3 +61,5 @@ pub mod count;
 pub mod event;
 mod ffi;
 pub mod sample;
+#[cfg(test)]
+mod test;
@@ -12,7 +12,17 @@ pub struct Arena {
 
 impl Arena {
     pub fn new(file: &File, len: usize, offset: usize) -> Result<Self> {
-        let prot = libc::PROT_READ | libc::PROT_WRITE;
+        Self::map(file, len, offset, libc::PROT_READ | libc::PROT_WRITE)
+    }
+
+    // The kernel overwrites old records instead of waiting for the
+    // tail if the ring buffer is mapped read-only:
+    // https://github.com/torvalds/linux/blob/v6.13/kernel/events/core.c
+    pub fn read_only(file: &File, len: usize, offset: usize) -> Result<Self> {
+        Self::map(file, len, offset, libc::PROT_READ)
+    }
+
+    fn map(file: &File, len: usize, offset: usize, prot: i32) -> Result<Self> {
         // https://github.com/torvalds/linux/blob/v6.13/kernel/events/core.c#L6582
         let flags = libc::MAP_SHARED;
         let ptr: NonNull<()> = syscall!(
@@ -9,14 +9,21 @@ use auxiliary::AuxTracer;
 use iter::{CowIter, Iter};
 use rb::Rb;
 use record::{Parser, UnsafeParser};
+use time::TscConv;
 
 use crate::ffi::{bindings as b, syscall, Attr, Metadata, PAGE_SIZE};
 
-mod arena;
+pub(crate) mod arena;
 pub mod auxiliary;
 pub mod iter;
+mod multi;
+mod overwrite;
 pub mod rb;
 pub mod record;
+pub mod time;
+
+pub use multi::*;
+pub use overwrite::*;
 
 /// Event sampler.
 ///
@@ -334,6 +341,13 @@ impl Sampler {
         let time_running = unsafe { AtomicU64::from_ptr(&mut metadata.time_running as _) };
         time_running.load(Ordering::Relaxed)
     }
+
+    /// Returns the conversion between hardware timestamps and perf time.
+    ///
+    /// Returns `None` if the kernel does not support it, see [`TscConv`] for details.
+    pub fn tsc_conv(&self) -> Option<TscConv> {
+        TscConv::from_metadata(self.metadata_inner())
+    }
 }
 
 // `Arena::ptr` is valid during the lifetime of `Sampler`.
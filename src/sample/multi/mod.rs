@@ -0,0 +1,296 @@
+#[cfg(test)]
+mod test;
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::os::fd::AsRawFd;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use super::iter::{AsyncCowIter, CowIter};
+use super::rb::CowChunk;
+use super::record::{Priv, Record, UnsafeParser};
+use super::time::TscConv;
+use super::Sampler;
+use crate::count::Counter;
+use crate::ffi::{bindings as b, syscall};
+
+/// Event sampler shared by multiple counters.
+///
+/// Records of all counters are written into the ring buffer of the first counter
+/// with [`PERF_EVENT_IOC_SET_OUTPUT`](https://man7.org/linux/man-pages/man2/perf_event_open.2.html),
+/// which saves memory and file descriptors compared to a [`Sampler`] per counter.
+/// Each record is parsed with the format of its counter, so counters can have
+/// different [`SampleFormat`][crate::config::SampleFormat]s.
+///
+/// Counters are told apart by their [event IDs][Counter::id], so all counters must be created
+/// with [`RecordIdFormat::identifier`][crate::config::RecordIdFormat::identifier] enabled, and
+/// the same [`Opts::record_id_all`][crate::config::Opts::record_id_all]. Without `record_id_all`,
+/// non-sample records can't be told apart and are parsed with the format of the first counter.
+///
+/// The kernel only allows redirecting records between counters on the same CPU,
+/// or on the same task if the counters are not bound to a CPU. Counters should
+/// also use the same [`Opts::timer`][crate::config::Opts::timer].
+///
+/// # Examples
+///
+/// ```rust
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use perf_event_open::config::{Cpu, Opts, Proc, SampleOn};
+/// use perf_event_open::count::Counter;
+/// use perf_event_open::event::sw::Software;
+/// use perf_event_open::sample::record::Record;
+/// use perf_event_open::sample::MultiSampler;
+///
+/// let target = (Proc::ALL, Cpu(0));
+/// let mut opts = Opts::default();
+/// opts.sample_on = SampleOn::Count(1_000_000); // 1ms
+/// opts.record_id_format.identifier = true;
+///
+/// let clock = Counter::new(Software::CpuClock, target, &opts).unwrap();
+/// opts.sample_on = SampleOn::Count(1);
+/// opts.sample_format.code_addr = true;
+/// let switches = Counter::new(Software::CtxSwitch, target, &opts).unwrap();
+///
+/// let mut sampler = MultiSampler::new(&clock, 5).unwrap();
+/// sampler.add(&switches).unwrap();
+///
+/// clock.enable().unwrap();
+/// switches.enable().unwrap();
+/// thread::sleep(Duration::from_millis(10));
+/// clock.disable().unwrap();
+/// switches.disable().unwrap();
+///
+/// let switches = switches.id().unwrap();
+/// for (id, _, record) in sampler.iter() {
+///     if let Record::Sample(s) = record {
+///         if id == Some(switches) {
+///             println!("context switch at {:?}", s.code_addr);
+///         }
+///     }
+/// }
+/// ```
+pub struct MultiSampler {
+    sampler: Sampler,
+    dispatch: Dispatch,
+    // Keep redirected counters from creating their own samplers,
+    // see `Counter::sampler` for details.
+    #[allow(dead_code)]
+    outputs: Vec<Arc<File>>,
+}
+
+impl MultiSampler {
+    /// Creates a sampler for the counter, which other counters can be added to.
+    ///
+    /// The sampler needs a ring buffer to store metadata and records,
+    /// and 1 + 2^`exp` pages will be allocated for this.
+    ///
+    /// See [`Counter::sampler`] for details.
+    pub fn new(counter: &Counter, exp: u8) -> Result<Self> {
+        let parser = parser(counter)?;
+        let id = counter.id()?;
+        let sampler = counter.sampler(exp)?;
+
+        let dispatch = Dispatch {
+            sample_id_all: parser.sample_id_all,
+            parsers: BTreeMap::from([(id, parser.clone())]),
+            default: parser,
+        };
+        Ok(Self {
+            sampler,
+            dispatch,
+            outputs: vec![],
+        })
+    }
+
+    /// Redirects records of the counter into the ring buffer of this sampler.
+    ///
+    /// The counter file must not be shared, i.e., the counter must not have a
+    /// [sampler][Counter::sampler] or be added to another `MultiSampler`, and
+    /// can't create a sampler after being added. Redirecting the records of a
+    /// counter whose file is shared would leave the other handles with an empty
+    /// ring buffer.
+    pub fn add(&mut self, counter: &Counter) -> Result<()> {
+        let parser = parser(counter)?;
+        if parser.sample_id_all != self.dispatch.sample_id_all {
+            let error = "`record_id_all` must be the same for all counters in the sampler.";
+            return Err(Error::new(ErrorKind::InvalidInput, error));
+        }
+        if Arc::strong_count(&counter.perf) > 1 {
+            let error = "The counter file is shared with a sampler or another handle.";
+            return Err(Error::new(ErrorKind::AlreadyExists, error));
+        }
+        let id = counter.id()?;
+
+        // `PERF_FLAG_FD_OUTPUT` does the same thing at creation, but it has been broken since
+        // `linux-2.6.35`, so the records are redirected after the counter was created:
+        // https://man7.org/linux/man-pages/man2/perf_event_open.2.html
+        syscall!(
+            unsafe,
+            ioctl_arg,
+            &counter.perf,
+            b::PERF_IOC_OP_SET_OUTPUT as u64,
+            self.sampler.perf.as_raw_fd() as u64
+        )?;
+
+        self.dispatch.parsers.insert(id, parser);
+        self.outputs.push(Arc::clone(&counter.perf));
+        Ok(())
+    }
+
+    /// Returns a record iterator over the kernel ring buffer.
+    ///
+    /// Records are returned with the event ID of their counter, see [`MultiIter`] for details.
+    pub fn iter(&self) -> MultiIter<'_> {
+        MultiIter {
+            inner: self.sampler.iter().into_cow(),
+            dispatch: &self.dispatch,
+        }
+    }
+
+    /// Returns the record parsers by event ID.
+    pub fn parsers(&self) -> &BTreeMap<u64, UnsafeParser> {
+        &self.dispatch.parsers
+    }
+
+    /// Pause the ring buffer output.
+    ///
+    /// See [`Sampler::pause`] for details.
+    pub fn pause(&self) -> Result<()> {
+        self.sampler.pause()
+    }
+
+    /// Resume the ring buffer output.
+    ///
+    /// See [`Sampler::resume`] for details.
+    pub fn resume(&self) -> Result<()> {
+        self.sampler.resume()
+    }
+
+    /// Returns the conversion between hardware timestamps and perf time.
+    ///
+    /// See [`Sampler::tsc_conv`] for details.
+    pub fn tsc_conv(&self) -> Option<TscConv> {
+        self.sampler.tsc_conv()
+    }
+}
+
+fn parser(counter: &Counter) -> Result<UnsafeParser> {
+    // We only change the attr fields related to event config,
+    // which are not used in `UnsafeParser::from_attr`.
+    let parser = UnsafeParser::from_attr(unsafe { &*counter.attr.get() });
+    // The type of `PERF_SAMPLE_IDENTIFIER` varies between binding versions.
+    let identifier: u64 = b::PERF_SAMPLE_IDENTIFIER as _;
+    if parser.sample_type & identifier == 0 {
+        let error = "Counters in the sampler must be created with `record_id_format.identifier`.";
+        return Err(Error::new(ErrorKind::InvalidInput, error));
+    }
+    Ok(parser)
+}
+
+struct Dispatch {
+    sample_id_all: bool,
+    parsers: BTreeMap<u64, UnsafeParser>,
+    // Parser of the first counter.
+    default: UnsafeParser,
+}
+
+impl Dispatch {
+    fn parse(&self, chunk: CowChunk<'_>) -> (Option<u64>, Priv, Record) {
+        let bytes = chunk.as_bytes();
+        let u64_at = |i: usize| {
+            bytes
+                .get(i..i + 8)
+                .map(|it| u64::from_ne_bytes(it.try_into().unwrap()))
+        };
+
+        // https://github.com/torvalds/linux/blob/v6.13/include/uapi/linux/perf_event.h#L824
+        // struct perf_event_header {
+        //     u32 type;
+        //     u16 misc;
+        //     u16 size;
+        // };
+        let ty = u32::from_ne_bytes(bytes[..4].try_into().unwrap());
+        let size = u16::from_ne_bytes(bytes[6..8].try_into().unwrap()) as usize;
+
+        // `PERF_SAMPLE_IDENTIFIER` is the first field of sample records,
+        // and the last field of other records if `sample_id_all` is set.
+        let id = match ty {
+            b::PERF_RECORD_SAMPLE => u64_at(8),
+            _ if self.sample_id_all && size >= 16 => u64_at(size - 8),
+            _ => None,
+        };
+        let parser = match id.and_then(|it| self.parsers.get(&it)) {
+            Some(it) => it,
+            None => &self.default,
+        };
+
+        // All counters writing to the ring buffer were added to the sampler with their
+        // parsers, and the parser is chosen by the ID of the counter generating the record.
+        let (p, r, _) = unsafe { parser.parse(bytes) };
+        (id, p, r)
+    }
+}
+
+/// Record iterator of [`MultiSampler`].
+///
+/// Each record is returned with the [event ID][Counter::id] of the counter generating it,
+/// which is `None` for non-sample records if [`Opts::record_id_all`][crate::config::Opts::record_id_all]
+/// is not enabled.
+pub struct MultiIter<'a> {
+    inner: CowIter<'a>,
+    dispatch: &'a Dispatch,
+}
+
+impl<'a> MultiIter<'a> {
+    /// Creates an asynchronous iterator.
+    pub fn into_async(self) -> Result<AsyncMultiIter<'a>> {
+        Ok(AsyncMultiIter {
+            inner: self.inner.into_async()?,
+            dispatch: self.dispatch,
+        })
+    }
+}
+
+impl Iterator for MultiIter<'_> {
+    type Item = (Option<u64>, Priv, Record);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let dispatch = self.dispatch;
+        self.inner.next(|cc, _| dispatch.parse(cc))
+    }
+}
+
+/// Asynchronous record iterator of [`MultiSampler`].
+pub struct AsyncMultiIter<'a> {
+    inner: AsyncCowIter<'a>,
+    dispatch: &'a Dispatch,
+}
+
+impl AsyncMultiIter<'_> {
+    /// Attempt to pull out the next value, registering the current task for
+    /// wakeup if the value is not yet available, and returning `None` if the
+    /// iterator is exhausted.
+    ///
+    /// [`WakeUpOn`][crate::config::WakeUpOn] of the first counter must be properly set to make this work.
+    pub fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<(Option<u64>, Priv, Record)>> {
+        let this = self.get_mut();
+        let dispatch = this.dispatch;
+        Pin::new(&mut this.inner).poll_next(cx, |cc, _| dispatch.parse(cc))
+    }
+
+    /// Advances the iterator and returns the next value.
+    ///
+    /// [`WakeUpOn`][crate::config::WakeUpOn] of the first counter must be properly set to make this work.
+    pub async fn next(&mut self) -> Option<(Option<u64>, Priv, Record)> {
+        let dispatch = self.dispatch;
+        self.inner.next(|cc, _| dispatch.parse(cc)).await
+    }
+}
@@ -0,0 +1,125 @@
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use super::MultiSampler;
+use crate::config::{Cpu, Opts, Proc, RecordIdFormat, SampleOn, Size};
+use crate::count::Counter;
+use crate::event::sw::Software;
+use crate::sample::record::Record;
+use crate::test::spin;
+
+fn opts() -> Opts {
+    Opts {
+        sample_on: SampleOn::Count(100_000), // 100us
+        record_id_format: RecordIdFormat {
+            identifier: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+#[test]
+fn test_dispatch() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let mut opts = opts();
+    opts.sample_format.code_addr = true;
+    let ip = Counter::new(Software::TaskClock, target, &opts).unwrap();
+    opts.sample_format.code_addr = false;
+    opts.sample_format.user_stack = Some(Size(8));
+    opts.sample_format.period = true;
+    let stack = Counter::new(Software::TaskClock, target, &opts).unwrap();
+
+    let mut sampler = MultiSampler::new(&ip, 5).unwrap();
+    sampler.add(&stack).unwrap();
+    assert_eq!(sampler.parsers().len(), 2);
+
+    ip.enable().unwrap();
+    stack.enable().unwrap();
+    spin(Duration::from_millis(10));
+    ip.disable().unwrap();
+    stack.disable().unwrap();
+
+    let (ip, stack) = (ip.id().unwrap(), stack.id().unwrap());
+    let (mut ips, mut stacks) = (0, 0);
+    for (id, _, record) in sampler.iter() {
+        let Record::Sample(s) = record else {
+            continue;
+        };
+        assert_eq!(s.record_id.id, id);
+        match id {
+            Some(id) if id == ip => {
+                assert!(s.user_stack.is_none());
+                assert!(s.period.is_none());
+                ips += 1;
+            }
+            Some(id) if id == stack => {
+                assert!(s.user_stack.is_some());
+                assert!(s.period.is_some());
+                stacks += 1;
+            }
+            _ => unreachable!(),
+        }
+    }
+    assert!(ips > 0);
+    assert!(stacks > 0);
+}
+
+#[test]
+fn test_record_id_all() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let mut opts = opts();
+    opts.record_id_all = true;
+    opts.extra_record.comm = true;
+    let first = Counter::new(Software::TaskClock, target, &opts).unwrap();
+    let second = Counter::new(Software::TaskClock, target, &opts).unwrap();
+
+    let mut sampler = MultiSampler::new(&first, 5).unwrap();
+    sampler.add(&second).unwrap();
+
+    first.enable().unwrap();
+    second.enable().unwrap();
+    let name = std::ffi::CString::new("multi").unwrap();
+    unsafe { libc::prctl(libc::PR_SET_NAME, name.as_ptr()) };
+    first.disable().unwrap();
+    second.disable().unwrap();
+
+    let ids = [first.id().unwrap(), second.id().unwrap()];
+    let comms: Vec<_> = sampler
+        .iter()
+        .filter(|(_, _, it)| matches!(it, Record::Comm(_)))
+        .map(|(id, _, _)| id.unwrap())
+        .collect();
+    assert!(ids.iter().all(|it| comms.contains(it)));
+}
+
+#[test]
+fn test_add() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let first = Counter::new(Software::TaskClock, target, opts()).unwrap();
+    let mut sampler = MultiSampler::new(&first, 5).unwrap();
+
+    // Without identifier.
+    let counter = Counter::new(Software::TaskClock, target, Opts::default()).unwrap();
+    let error = sampler.add(&counter).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    let error = MultiSampler::new(&counter, 5).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+    // With different `record_id_all`.
+    let mut record_id_all = opts();
+    record_id_all.record_id_all = true;
+    let counter = Counter::new(Software::TaskClock, target, record_id_all).unwrap();
+    let error = sampler.add(&counter).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+    // Already has a sampler.
+    let error = sampler.add(&first).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+
+    // Can't create a sampler after being added.
+    let counter = Counter::new(Software::TaskClock, target, opts()).unwrap();
+    sampler.add(&counter).unwrap();
+    let error = counter.sampler(5).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+}
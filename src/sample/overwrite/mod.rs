@@ -0,0 +1,235 @@
+// `write_backward` is since `linux-4.7`.
+#[cfg(all(test, feature = "linux-4.7"))]
+mod test;
+
+use std::fs::File;
+use std::io::{Error, ErrorKind, Result};
+use std::ptr::copy_nonoverlapping;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use super::arena::Arena;
+use super::record::{Priv, Record, UnsafeParser};
+use super::time::TscConv;
+use crate::ffi::{Attr, Metadata, PAGE_SIZE};
+
+/// Event sampler in overwrite mode.
+///
+/// Unlike [`Sampler`][super::Sampler], the kernel never waits for records to be
+/// consumed, but keeps overwriting the oldest records in the ring buffer, similar
+/// to the `perf record --overwrite` command. This makes an always-on flight
+/// recorder whose most recent records can be dumped on demand, e.g., when a
+/// latency threshold is exceeded.
+///
+/// The counter must be created with [`Opts::write_backward`][crate::config::Opts::write_backward],
+/// so that the most recent record can be found from the ring buffer head.
+///
+/// # Examples
+///
+/// ```rust
+/// # #[cfg(not(feature = "linux-4.7"))]
+/// # return;
+/// #
+/// use std::thread;
+/// use std::time::Duration;
+///
+/// use perf_event_open::config::{Cpu, Opts, Proc, SampleOn};
+/// use perf_event_open::count::Counter;
+/// use perf_event_open::event::sw::Software;
+///
+/// let target = (Proc::ALL, Cpu(0));
+/// let mut opts = Opts::default();
+/// opts.sample_on = SampleOn::Count(100_000); // 100us
+/// opts.sample_format.code_addr = true;
+/// opts.record_id_format.time = true;
+/// opts.write_backward = true;
+///
+/// let counter = Counter::new(Software::CpuClock, target, opts).unwrap();
+/// let sampler = counter.overwrite_sampler(2).unwrap();
+///
+/// counter.enable().unwrap();
+/// thread::sleep(Duration::from_millis(100));
+///
+/// // Something bad happened, dump the most recent samples.
+/// let snapshot = sampler.snapshot().unwrap();
+/// for it in snapshot.iter() {
+///     println!("{:-?}", it);
+/// }
+/// ```
+pub struct OverwriteSampler {
+    perf: Arc<File>,
+    arena: Arena,
+    parser: UnsafeParser,
+}
+
+impl OverwriteSampler {
+    pub(crate) fn new(perf: Arc<File>, attr: &Attr, exp: u8) -> Result<Self> {
+        #[cfg(feature = "linux-4.7")]
+        let write_backward = attr.write_backward() > 0;
+        #[cfg(not(feature = "linux-4.7"))]
+        let write_backward = false;
+        if !write_backward {
+            let error = "The counter must be created with `write_backward`.";
+            return Err(Error::new(ErrorKind::InvalidInput, error));
+        }
+
+        let Some(len) = 2_usize
+            .checked_pow(exp as u32)
+            .and_then(|n| n.checked_add(1))
+            .and_then(|n| n.checked_mul(*PAGE_SIZE))
+        else {
+            return Err(Error::other("allocation size overflow"));
+        };
+        let arena = Arena::read_only(&perf, len, 0)?;
+
+        Ok(Self {
+            perf,
+            arena,
+            parser: UnsafeParser::from_attr(attr),
+        })
+    }
+
+    /// Takes a snapshot of the records in the ring buffer.
+    ///
+    /// The ring buffer output is paused while copying records out, so the snapshot
+    /// is consistent. Records generated during the pause are lost.
+    ///
+    /// Records are not consumed, so they can show up again in the next snapshot
+    /// if they have not been overwritten.
+    pub fn snapshot(&self) -> Result<Snapshot> {
+        self.pause_output(true)?;
+        let snapshot = self.copy();
+        self.pause_output(false)?;
+        Ok(snapshot)
+    }
+
+    fn pause_output(&self, pause: bool) -> Result<()> {
+        // Both `write_backward` and `PERF_EVENT_IOC_PAUSE_OUTPUT` are since `linux-4.7`.
+        #[cfg(feature = "linux-4.7")]
+        return {
+            use crate::ffi::{bindings as b, syscall};
+
+            syscall!(
+                unsafe,
+                ioctl_arg,
+                &self.perf,
+                b::PERF_IOC_OP_PAUSE_OUTPUT as u64,
+                pause as u64
+            )?;
+            Ok(())
+        };
+        #[cfg(not(feature = "linux-4.7"))]
+        return {
+            let _ = (&self.perf, pause);
+            Err(ErrorKind::Unsupported.into())
+        };
+    }
+
+    fn copy(&self) -> Snapshot {
+        let alloc = self.arena.as_slice();
+        let metadata = unsafe { &mut *(alloc.as_ptr() as *mut Metadata) };
+        let head = unsafe { AtomicU64::from_ptr(&mut metadata.data_head as _) };
+        // https://github.com/torvalds/linux/blob/v6.13/kernel/events/core.c#L6212
+        let rb = &alloc[*PAGE_SIZE..];
+        let size = rb.len() as u64;
+        let mask = size - 1;
+
+        // Records are written backwards, so the head points to the most recent record,
+        // and the older records follow it. Records are 8-byte aligned, so the header
+        // never wraps around. The unwritten area is zeroed. See also:
+        // https://github.com/torvalds/linux/blob/v6.13/tools/lib/perf/mmap.c
+        let head = head.load(Ordering::Acquire);
+        let mut records = vec![];
+        let mut pos = head;
+        while pos.wrapping_sub(head) < size {
+            let offset = (pos & mask) as usize;
+            let len = u16::from_ne_bytes([rb[offset + 6], rb[offset + 7]]) as u64;
+            // The oldest record may be partially overwritten.
+            if len < 8 || pos.wrapping_sub(head) + len > size {
+                break;
+            }
+            records.push((offset, len as usize));
+            pos = pos.wrapping_add(len);
+        }
+
+        // Copy records in chronological order.
+        let total = records.iter().map(|(_, len)| len).sum::<usize>();
+        let mut buf = vec![0_u64; total / size_of::<u64>()];
+        let dst = buf.as_mut_ptr() as *mut u8;
+        let mut copied = 0;
+        for &(offset, len) in records.iter().rev() {
+            let first = len.min(rb.len() - offset);
+            unsafe {
+                copy_nonoverlapping(rb.as_ptr().add(offset), dst.add(copied), first);
+                copy_nonoverlapping(rb.as_ptr(), dst.add(copied + first), len - first);
+            }
+            copied += len;
+        }
+
+        Snapshot {
+            buf,
+            parser: self.parser.clone(),
+        }
+    }
+
+    /// Record parser of the sampler.
+    pub fn parser(&self) -> &UnsafeParser {
+        &self.parser
+    }
+
+    /// Returns the conversion between hardware timestamps and perf time.
+    ///
+    /// See [`Sampler::tsc_conv`][super::Sampler::tsc_conv] for details.
+    pub fn tsc_conv(&self) -> Option<TscConv> {
+        TscConv::from_metadata(self.arena.as_slice().as_ptr() as *mut Metadata)
+    }
+}
+
+// `Arena::ptr` is valid during the lifetime of `OverwriteSampler`.
+unsafe impl Send for OverwriteSampler {}
+
+/// Records copied out of an [`OverwriteSampler`].
+#[derive(Clone)]
+pub struct Snapshot {
+    // `u64` for 8-byte alignment.
+    buf: Vec<u64>,
+    parser: UnsafeParser,
+}
+
+impl Snapshot {
+    /// Returns the raw bytes of records, from the oldest to the most recent.
+    ///
+    /// The bytes can be parsed with [`OverwriteSampler::parser`] later.
+    pub fn as_bytes(&self) -> &[u8] {
+        let len = self.buf.len() * size_of::<u64>();
+        unsafe { std::slice::from_raw_parts(self.buf.as_ptr() as *const u8, len) }
+    }
+
+    /// Returns an iterator over the records, from the oldest to the most recent.
+    pub fn iter(&self) -> SnapshotIter<'_> {
+        SnapshotIter {
+            bytes: self.as_bytes(),
+            parser: &self.parser,
+        }
+    }
+}
+
+/// Record iterator of [`Snapshot`].
+pub struct SnapshotIter<'a> {
+    bytes: &'a [u8],
+    parser: &'a UnsafeParser,
+}
+
+impl Iterator for SnapshotIter<'_> {
+    type Item = (Priv, Record);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+        // The bytes are copied record by record from the same sampler.
+        let (p, r, len) = unsafe { self.parser.parse(self.bytes) };
+        self.bytes = &self.bytes[len..];
+        Some((p, r))
+    }
+}
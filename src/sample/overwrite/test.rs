@@ -0,0 +1,69 @@
+use std::io::ErrorKind;
+use std::time::Duration;
+
+use crate::config::{Cpu, Opts, Proc, RecordIdFormat, SampleOn};
+use crate::count::Counter;
+use crate::event::sw::Software;
+use crate::ffi::PAGE_SIZE;
+use crate::sample::record::Record;
+use crate::test::spin;
+
+fn times(snapshot: &super::Snapshot) -> Vec<u64> {
+    snapshot
+        .iter()
+        // The kernel may also write `Throttle` records.
+        .filter_map(|(_, it)| match it {
+            Record::Sample(s) => Some(s.record_id.time.unwrap()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_snapshot() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let opts = Opts {
+        sample_on: SampleOn::Count(50_000), // 50us
+        record_id_format: RecordIdFormat {
+            time: true,
+            ..Default::default()
+        },
+        write_backward: true,
+        ..Default::default()
+    };
+    let counter = Counter::new(Software::TaskClock, target, opts).unwrap();
+    let sampler = counter.overwrite_sampler(0).unwrap();
+
+    // Empty ring buffer.
+    assert!(sampler.snapshot().unwrap().as_bytes().is_empty());
+
+    // Generate more samples than the ring buffer can hold.
+    counter.enable().unwrap();
+    spin(Duration::from_millis(50));
+    let snapshot = sampler.snapshot().unwrap();
+    let len = snapshot.as_bytes().len();
+    // The largest record is `Throttle`: 8-byte header + time + ID + stream ID.
+    assert!(len <= *PAGE_SIZE && len > *PAGE_SIZE - 32);
+    let first = times(&snapshot);
+    assert!(first.windows(2).all(|it| it[0] <= it[1]));
+
+    // Output is resumed after the snapshot.
+    spin(Duration::from_millis(20));
+    counter.disable().unwrap();
+    let second = times(&sampler.snapshot().unwrap());
+    assert!(second.windows(2).all(|it| it[0] <= it[1]));
+    assert!(second.last() > first.last());
+}
+
+#[test]
+fn test_not_backward() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let counter = Counter::new(Software::TaskClock, target, Opts::default()).unwrap();
+    let error = counter.overwrite_sampler(0).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+
+    let sampler = counter.sampler(0).unwrap();
+    let error = counter.overwrite_sampler(0).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::AlreadyExists);
+    drop(sampler);
+}
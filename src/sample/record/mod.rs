@@ -270,18 +270,12 @@ impl RecordId {
         let stream_id = when!(PERF_SAMPLE_STREAM_ID, u64);
         let cpu = when!(PERF_SAMPLE_CPU, u32);
 
-        // For `PERF_SAMPLE_IDENTIFIER`:
-        // `PERF_SAMPLE_IDENTIFIER` just duplicates the `PERF_SAMPLE_ID` at a fixed offset,
-        // it's useful to distinguish the sample format if multiple events share the same rb.
-        // Our design does not support redirecting samples to another rb (e.g., `PERF_FLAG_FD_OUTPUT`),
-        // and this is not a parser crate, so `PERF_SAMPLE_IDENTIFIER` is not needed.
-        // See:
-        // https://github.com/torvalds/linux/blob/v6.13/kernel/events/core.c#L7342
-        // https://github.com/torvalds/linux/blob/v6.13/tools/perf/Documentation/perf.data-file-format.txt#L466
-        // https://github.com/torvalds/linux/blob/v6.13/kernel/events/core.c#L12808
+        // `PERF_SAMPLE_IDENTIFIER` duplicates the `PERF_SAMPLE_ID` at a fixed offset (the end
+        // of record), see `Sample::from_ptr` for details.
+        let identifier = when!(PERF_SAMPLE_IDENTIFIER, u64);
 
         Self {
-            id,
+            id: id.or(identifier),
             stream_id,
             cpu,
             task,
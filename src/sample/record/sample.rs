@@ -189,15 +189,12 @@ impl Sample {
             }};
         }
 
-        // For `PERF_SAMPLE_IDENTIFIER`:
-        // `PERF_SAMPLE_IDENTIFIER` just duplicates the `PERF_SAMPLE_ID` at a fixed offset,
-        // it's useful to distinguish the sample format if multiple events share the same rb.
-        // Our design does not support redirecting samples to another rb (e.g., `PERF_FLAG_FD_OUTPUT`),
-        // and this is not a parser crate, so `PERF_SAMPLE_IDENTIFIER` is not needed.
+        // `PERF_SAMPLE_IDENTIFIER` duplicates the `PERF_SAMPLE_ID` at a fixed offset, so
+        // that the sample format can be distinguished if multiple events share the same rb.
         // See:
         // https://github.com/torvalds/linux/blob/v6.13/kernel/events/core.c#L7342
         // https://github.com/torvalds/linux/blob/v6.13/tools/perf/Documentation/perf.data-file-format.txt#L466
-        // https://github.com/torvalds/linux/blob/v6.13/kernel/events/core.c#L12808
+        let identifier = when!(PERF_SAMPLE_IDENTIFIER, u64);
 
         let code_addr = when!(PERF_SAMPLE_IP, {
             (
@@ -288,7 +285,7 @@ impl Sample {
 
         Self {
             record_id: RecordId {
-                id,
+                id: id.or(identifier),
                 stream_id,
                 cpu,
                 task,
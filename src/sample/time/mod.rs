@@ -0,0 +1,245 @@
+#[cfg(test)]
+mod test;
+
+use std::io::{Error, Result};
+use std::ptr::{addr_of, read_volatile};
+use std::sync::atomic::{compiler_fence, Ordering};
+use std::time::Duration;
+
+use crate::config::Clock;
+use crate::ffi::Metadata;
+
+// `cap_user_time*` in `perf_event_mmap_page`:
+// https://github.com/torvalds/linux/blob/v6.13/include/uapi/linux/perf_event.h
+const CAP_USER_TIME_ZERO: u64 = 1 << 4;
+const CAP_USER_TIME_SHORT: u64 = 1 << 5;
+
+/// Conversion between hardware timestamps and perf time.
+///
+/// This is a snapshot of the `time_*` fields of the metadata page, which the
+/// kernel exposes if the perf clock is derived from a stable hardware counter
+/// (`cap_user_time_zero`), i.e., the TSC on x86 or the generic timer on arm64.
+///
+/// Perf time is the time of [`RecordId::time`][super::record::RecordId::time]
+/// if [`Opts::timer`][crate::config::Opts::timer] is not set. Samples with
+/// a custom timer are already in that clock and need no conversion.
+///
+/// The conversion parameters may change (e.g., on TSC frequency recalibration),
+/// so the snapshot should be taken again for long-running sessions.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::{Duration, SystemTime};
+///
+/// use perf_event_open::config::{Clock, Cpu, Opts, Proc};
+/// use perf_event_open::count::Counter;
+/// use perf_event_open::event::sw::Software;
+/// use perf_event_open::sample::time::TscConv;
+///
+/// let target = (Proc::CURRENT, Cpu::ALL);
+/// let counter = Counter::new(Software::TaskClock, target, Opts::default()).unwrap();
+/// let sampler = counter.sampler(5).unwrap();
+///
+/// if let Some(conv) = sampler.tsc_conv() {
+///     let tsc = TscConv::read_tsc().unwrap();
+///     let time = conv.tsc_to_time(tsc);
+///
+///     // Convert perf time to wall-clock time.
+///     let offset = conv.clock_offset(Clock::RealTime).unwrap();
+///     let nanos = (time as i64 + offset) as u64;
+///     let wall = SystemTime::UNIX_EPOCH + Duration::from_nanos(nanos);
+///     println!("{} => {:?}", tsc, wall);
+/// }
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TscConv {
+    /// Shift of the cycles to nanoseconds multiplier.
+    pub time_shift: u16,
+
+    /// Cycles to nanoseconds multiplier.
+    pub time_mult: u32,
+
+    /// Perf time when the hardware counter was zero.
+    pub time_zero: u64,
+
+    /// Base cycles of the wrapped hardware counter.
+    ///
+    /// This is used when the hardware counter is shorter than 64 bits
+    /// (`cap_user_time_short`), otherwise it is zero.
+    pub time_cycles: u64,
+
+    /// Mask of the wrapped hardware counter.
+    ///
+    /// This is used when the hardware counter is shorter than 64 bits
+    /// (`cap_user_time_short`), otherwise it is zero.
+    pub time_mask: u64,
+}
+
+impl TscConv {
+    // Returns `None` if the page does not provide the `time_zero` field,
+    // or the hardware counter is short but the `time_{cycles,mask}` fields
+    // are not available in the selected kernel version.
+    pub(crate) fn from_metadata(metadata: *const Metadata) -> Option<Self> {
+        macro_rules! field {
+            ($field:ident) => {
+                unsafe { read_volatile(addr_of!((*metadata).$field)) }
+            };
+        }
+
+        loop {
+            let seq = field!(lock);
+            compiler_fence(Ordering::SeqCst);
+
+            let caps =
+                unsafe { read_volatile(addr_of!((*metadata).__bindgen_anon_1.capabilities)) };
+            if caps & CAP_USER_TIME_ZERO == 0 {
+                return None;
+            }
+            let short = caps & CAP_USER_TIME_SHORT > 0;
+            // Since `linux-5.9`:
+            // https://github.com/torvalds/linux/blob/v5.9/include/uapi/linux/perf_event.h
+            #[cfg(feature = "linux-5.9")]
+            let (time_cycles, time_mask) = match short {
+                true => (field!(time_cycles), field!(time_mask)),
+                false => (0, 0),
+            };
+            #[cfg(not(feature = "linux-5.9"))]
+            let (time_cycles, time_mask) = match short {
+                true => return None,
+                false => (0, 0),
+            };
+            let conv = Self {
+                time_shift: field!(time_shift),
+                time_mult: field!(time_mult),
+                time_zero: field!(time_zero),
+                time_cycles,
+                time_mask,
+            };
+
+            compiler_fence(Ordering::SeqCst);
+            if field!(lock) == seq {
+                return Some(conv);
+            }
+        }
+    }
+
+    /// Converts hardware counter cycles to perf time in nanoseconds.
+    pub fn tsc_to_time(&self, tsc: u64) -> u64 {
+        // Same as the example in the comments of `perf_event_mmap_page::time_zero`:
+        // https://github.com/torvalds/linux/blob/v6.13/include/uapi/linux/perf_event.h
+        let mut cyc = tsc;
+        if self.time_mask > 0 {
+            cyc = self
+                .time_cycles
+                .wrapping_add(cyc.wrapping_sub(self.time_cycles) & self.time_mask);
+        }
+        // Multiply in 128 bits instead of splitting the cycles into quotient and remainder.
+        let time = (cyc as u128 * self.time_mult as u128) >> self.time_shift;
+        self.time_zero.wrapping_add(time as u64)
+    }
+
+    /// Converts perf time in nanoseconds to hardware counter cycles.
+    ///
+    /// The result is truncated to the counter width if the counter is short.
+    pub fn time_to_tsc(&self, time: u64) -> u64 {
+        // Same as the example in the comments of `perf_event_mmap_page::time_zero`:
+        // https://github.com/torvalds/linux/blob/v6.13/include/uapi/linux/perf_event.h
+        if self.time_mult == 0 {
+            return 0;
+        }
+        let time = time.wrapping_sub(self.time_zero);
+        let cyc = ((time as u128) << self.time_shift) / self.time_mult as u128;
+        let cyc = cyc as u64;
+        if self.time_mask > 0 {
+            cyc & self.time_mask
+        } else {
+            cyc
+        }
+    }
+
+    /// Returns the current perf time.
+    ///
+    /// Returns `None` if the hardware counter can't be read on this architecture.
+    pub fn now(&self) -> Option<u64> {
+        Self::read_tsc().map(|it| self.tsc_to_time(it))
+    }
+
+    /// Returns the offset in nanoseconds from perf time to the given clock.
+    ///
+    /// The clock time of a perf timestamp is `time as i64 + offset`.
+    ///
+    /// The offset is measured by reading the clock between two reads of the
+    /// hardware counter, so it has an error of tens of nanoseconds. It changes
+    /// when the clock is adjusted (e.g., by NTP or the system administrator
+    /// for [`Clock::RealTime`]), and should be measured again as needed.
+    ///
+    /// Returns `None` if the hardware counter can't be read on this architecture.
+    pub fn clock_offset(&self, clock: Clock) -> Option<i64> {
+        let clockid = match clock {
+            Clock::Tai => libc::CLOCK_TAI,
+            Clock::RealTime => libc::CLOCK_REALTIME,
+            Clock::BootTime => libc::CLOCK_BOOTTIME,
+            Clock::Monotonic => libc::CLOCK_MONOTONIC,
+            Clock::MonotonicRaw => libc::CLOCK_MONOTONIC_RAW,
+        };
+
+        // Take the sample with the shortest window to reduce the error.
+        let mut best: Option<(u64, i64)> = None;
+        for _ in 0..8 {
+            let begin = Self::read_tsc()?;
+            let clock_time = clock_gettime(clockid).ok()?;
+            let end = Self::read_tsc()?;
+
+            let begin = self.tsc_to_time(begin);
+            let end = self.tsc_to_time(end);
+            let window = end.wrapping_sub(begin);
+            let time = begin.wrapping_add(window / 2);
+            let offset = clock_time.wrapping_sub(time as i64);
+            if best.map_or(true, |(it, _)| window < it) {
+                best = Some((window, offset));
+            }
+        }
+        best.map(|(_, offset)| offset)
+    }
+
+    /// Reads the hardware counter used by perf time.
+    ///
+    /// This is the TSC on x86 and the virtual counter (`cntvct_el0`) on arm64.
+    /// Returns `None` on other architectures.
+    pub fn read_tsc() -> Option<u64> {
+        read_tsc()
+    }
+}
+
+fn clock_gettime(clockid: libc::clockid_t) -> Result<i64> {
+    let mut ts = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    if unsafe { libc::clock_gettime(clockid, &mut ts) } != 0 {
+        return Err(Error::last_os_error());
+    }
+    let time = Duration::new(ts.tv_sec as u64, ts.tv_nsec as u32);
+    Ok(time.as_nanos() as i64)
+}
+
+#[cfg(target_arch = "x86_64")]
+fn read_tsc() -> Option<u64> {
+    Some(unsafe { std::arch::x86_64::_rdtsc() })
+}
+
+#[cfg(target_arch = "aarch64")]
+fn read_tsc() -> Option<u64> {
+    let val: u64;
+    unsafe {
+        std::arch::asm!("isb", "mrs {}, cntvct_el0", out(reg) val, options(nostack, nomem));
+    }
+    Some(val)
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn read_tsc() -> Option<u64> {
+    None
+}
@@ -0,0 +1,67 @@
+use super::TscConv;
+use crate::config::{Clock, Cpu, Opts, Proc};
+use crate::count::Counter;
+use crate::event::sw::Software;
+
+#[test]
+fn test_conv() {
+    // 1 GHz counter.
+    let conv = TscConv {
+        time_shift: 10,
+        time_mult: 1 << 10,
+        time_zero: 1000,
+        time_cycles: 0,
+        time_mask: 0,
+    };
+    assert_eq!(conv.tsc_to_time(0), 1000);
+    assert_eq!(conv.tsc_to_time(500), 1500);
+    assert_eq!(conv.time_to_tsc(1500), 500);
+
+    // 4 GHz counter, no overflow on large cycles.
+    let conv = TscConv {
+        time_shift: 12,
+        time_mult: 1 << 10,
+        ..conv
+    };
+    assert_eq!(conv.tsc_to_time(4000), 2000);
+    assert_eq!(conv.time_to_tsc(2000), 4000);
+    let tsc = 1 << 62;
+    assert_eq!(conv.time_to_tsc(conv.tsc_to_time(tsc)), tsc);
+}
+
+#[test]
+fn test_short() {
+    // 16-bit counter.
+    let conv = TscConv {
+        time_shift: 0,
+        time_mult: 1,
+        time_zero: 0,
+        time_cycles: 0x10000,
+        time_mask: 0xffff,
+    };
+    assert_eq!(conv.tsc_to_time(0x10), 0x10010);
+    assert_eq!(conv.tsc_to_time(0xfff0), 0x1fff0);
+    assert_eq!(conv.time_to_tsc(0x10010), 0x10);
+}
+
+#[test]
+fn test_metadata() {
+    let target = (Proc::CURRENT, Cpu::ALL);
+    let counter = Counter::new(Software::TaskClock, target, Opts::default()).unwrap();
+    let reader = counter.fast_reader().unwrap();
+    let Some(conv) = reader.tsc_conv() else {
+        return;
+    };
+    let Some(now) = conv.now() else {
+        return;
+    };
+
+    // Perf time is close to the monotonic clock.
+    let offset = conv.clock_offset(Clock::Monotonic).unwrap();
+    let monotonic = super::clock_gettime(libc::CLOCK_MONOTONIC).unwrap();
+    let diff = monotonic - (now as i64 + offset);
+    assert!(diff.abs() < 1_000_000_000);
+
+    let tsc = conv.time_to_tsc(now);
+    assert!(conv.tsc_to_time(tsc).abs_diff(now) <= 1);
+}
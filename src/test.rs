@@ -0,0 +1,21 @@
+//! Helpers shared by the tests of multiple modules.
+
+use std::hint::{black_box, spin_loop};
+use std::time::{Duration, Instant};
+
+/// Runs a fixed amount of work for the counters to count.
+pub fn busy_loop() {
+    let mut sum = 0_u64;
+    for i in 0..100000 {
+        sum = sum.wrapping_add(i * i);
+    }
+    black_box(sum);
+}
+
+/// Spins on the current thread for the duration.
+pub fn spin(duration: Duration) {
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        spin_loop();
+    }
+}